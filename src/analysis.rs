@@ -0,0 +1,100 @@
+use crate::{
+    dungeon::Dungeon,
+    game_text::GameText,
+};
+
+/// What the second word of a floor table (see
+/// [`Floor::unknown_word`](crate::Floor::unknown_word)) would point to if it
+/// were a pointer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WordTarget {
+    /// The word is zero.
+    Null,
+
+    /// The word is beyond the end of the file.
+    OutOfRange,
+
+    /// The word is the offset of a byte not part of any known structure.
+    Unclaimed,
+
+    /// The word is the offset of a byte of a known structure.
+    Region {
+        /// What the structure is (see
+        /// [`Region::description`](crate::coverage::Region::description)).
+        description: String,
+
+        /// How far into the structure the byte is.
+        offset: usize,
+    },
+}
+
+impl WordTarget {
+    /// Whether the word looks like a pointer: a non-zero, four-byte aligned
+    /// offset of the start of a known structure, as all the pointers the
+    /// game is known to use are.
+    pub fn is_likely_pointer(
+        &self,
+        value: u32,
+    ) -> bool {
+        value & 3 == 0
+            && matches!(self, WordTarget::Region {
+                offset: 0,
+                ..
+            })
+    }
+}
+
+/// The second word of the table of one floor of a dungeon, and what it
+/// would point to if it were a pointer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FloorWordReport {
+    /// The number of the floor, counting from 1.
+    pub floor: usize,
+
+    /// The name of the floor.
+    pub name: GameText,
+
+    /// The value of the word.
+    pub value: u32,
+
+    /// What the word would point to if it were a pointer.
+    pub target: WordTarget,
+}
+
+/// Analyze the second word of each floor table of the given dungeon file,
+/// whose meaning is not known (see
+/// [`Floor::unknown_word`](crate::Floor::unknown_word)).
+pub fn floor_word_report(raw: &[u8]) -> anyhow::Result<Vec<FloorWordReport>> {
+    let (dungeon, coverage) = Dungeon::parse_with_coverage(raw)?;
+    let regions = coverage.regions();
+    Ok(dungeon
+        .floors()
+        .iter()
+        .enumerate()
+        .map(|(i, floor)| {
+            let value = floor.unknown_word();
+            let offset = value as usize;
+            let target = if value == 0 {
+                WordTarget::Null
+            } else if offset >= raw.len() {
+                WordTarget::OutOfRange
+            } else {
+                regions
+                    .iter()
+                    .find(|region| region.range.contains(&offset))
+                    .map_or(WordTarget::Unclaimed, |region| {
+                        WordTarget::Region {
+                            description: region.description.clone(),
+                            offset: offset - region.range.start,
+                        }
+                    })
+            };
+            FloorWordReport {
+                floor: i + 1,
+                name: floor.name().clone(),
+                value,
+                target,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,106 @@
+use crate::{
+    floor_plan::{
+        FloorPlan,
+        Tile,
+    },
+    layout::Layout,
+    warp::WarpKind,
+};
+
+/// Explanation of the characters used by [`render_map`].
+pub const LEGEND: &str = "\
+Tiles:
+  # empty     . room      : corridor  ~ water
+  ^ fire      \" nature    = machine   ; dark
+  7 9 A-F unknown tile code
+Entities:
+  S spawn warp      N next floor warp   E exit warp
+  W unknown warp    C chest             T trap
+  D Digimon
+";
+
+/// Return the character used to draw the given tile.  Tiles with unknown
+/// codes are drawn as the hexadecimal digit of their code.
+pub fn tile_glyph(tile: Tile) -> char {
+    match tile {
+        Tile::Empty => '#',
+        Tile::Room => '.',
+        Tile::Corridor => ':',
+        Tile::Water => '~',
+        Tile::Fire => '^',
+        Tile::Nature => '"',
+        Tile::Machine => '=',
+        Tile::Dark => ';',
+        Tile::Unknown(code) => {
+            std::char::from_digit(u32::from(code & 0x0F), 16)
+                .unwrap_or('?')
+                .to_ascii_uppercase()
+        },
+    }
+}
+
+/// Return the tile drawn with the given character, if any.  This is the
+/// inverse of [`tile_glyph`], so the digits of the codes of known tiles are
+/// not accepted.
+pub fn tile_from_glyph(glyph: char) -> Option<Tile> {
+    match glyph {
+        '#' => Some(Tile::Empty),
+        '.' => Some(Tile::Room),
+        ':' => Some(Tile::Corridor),
+        '~' => Some(Tile::Water),
+        '^' => Some(Tile::Fire),
+        '"' => Some(Tile::Nature),
+        '=' => Some(Tile::Machine),
+        ';' => Some(Tile::Dark),
+        glyph => glyph
+            .to_digit(16)
+            .filter(|_| !glyph.is_ascii_lowercase())
+            .map(|code| Tile::from(code as u8))
+            .filter(|tile| matches!(tile, Tile::Unknown(_))),
+    }
+}
+
+/// Draw the floor plan of the given layout, one character per tile, with
+/// its warps, chests, traps and Digimon drawn over the tiles where they are
+/// placed.  When more than one entity occupies a tile, warps are drawn over
+/// chests, chests over traps, and traps over Digimon.
+pub fn render_map(layout: &Layout) -> String {
+    let floor_plan = layout.floor_plan();
+    let mut grid = (0..FloorPlan::HEIGHT)
+        .map(|y| {
+            (0..FloorPlan::WIDTH)
+                .map(|x| tile_glyph(floor_plan.tile(x, y).unwrap()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut overlay = |x: u8, y: u8, glyph: char| {
+        if let Some(cell) = grid
+            .get_mut(usize::from(y))
+            .and_then(|row| row.get_mut(usize::from(x)))
+        {
+            *cell = glyph;
+        }
+    };
+    for spawn in layout.digimon() {
+        overlay(spawn.x, spawn.y, 'D');
+    }
+    for trap in layout.traps() {
+        overlay(trap.x, trap.y, 'T');
+    }
+    for chest in layout.chests() {
+        overlay(chest.x, chest.y, 'C');
+    }
+    for warp in layout.warps() {
+        overlay(warp.x, warp.y, match warp.kind {
+            WarpKind::Spawn => 'S',
+            WarpKind::NextFloor => 'N',
+            WarpKind::Exit => 'E',
+            WarpKind::Unknown(_) => 'W',
+        });
+    }
+    grid.into_iter().fold(String::new(), |mut map, row| {
+        map.extend(row);
+        map.push('\n');
+        map
+    })
+}
@@ -0,0 +1,206 @@
+use crate::text::CHARACTER_MAP;
+use anyhow::{
+    anyhow,
+    Context as _,
+};
+use once_cell::sync::Lazy;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    path::Path,
+};
+
+static BUILTIN: Lazy<CharacterTable> = Lazy::new(|| CharacterTable {
+    entries: CHARACTER_MAP
+        .iter()
+        .map(|(code, text)| (*code, String::from(*text)))
+        .collect(),
+});
+
+/// A mapping from character codes used in the game's text encoding to the
+/// text they represent.  Codes in the range `0xF000`-`0xF0FF` are two-byte
+/// codes for dictionary words.
+///
+/// The built-in table ([`CharacterTable::builtin`], from
+/// [`CHARACTER_MAP`]) covers the English release of the game, with some
+/// gaps.  Tables can also be loaded from `.tbl` files (see
+/// [`CharacterTable::from_tbl`]) and merged over the built-in table to fill
+/// those gaps or to support other releases.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CharacterTable {
+    entries: HashMap<u16, String>,
+}
+
+impl CharacterTable {
+    /// The table built into this crate.
+    pub fn builtin() -> &'static Self {
+        &BUILTIN
+    }
+
+    /// Encode a string in the game's text encoding, ending with `0xFF`.
+    /// This is the inverse of [`CharacterTable::parse_string`].
+    ///
+    /// At each point in the text, the longest piece of text which has an
+    /// encoding is used.  Dictionary words (two-byte codes `0xF0xx`, such
+    /// as "Digimon" or "Domain") are only used where they make the result
+    /// shorter than spelling them out.  Control codes are written as in
+    /// decoded text, such as `<ENTER>` or `<NEW BOX>`.
+    pub fn encode_string(
+        &self,
+        text: &str,
+    ) -> anyhow::Result<Vec<u8>> {
+        // Try longest text first, and single-byte codes before dictionary
+        // words of the same length.
+        let mut encodings = self
+            .entries
+            .iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(code, text)| (text.as_str(), *code))
+            .collect::<Vec<_>>();
+        encodings.sort_by_key(|(text, code)| (Reverse(text.len()), *code));
+        let mut encoded = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let (piece, code) = encodings
+                .iter()
+                .find(|(piece, code)| {
+                    rest.starts_with(piece)
+                        && (*code <= 0xFF || piece.chars().count() > 2)
+                })
+                .ok_or_else(|| {
+                    let position = text.len() - rest.len();
+                    anyhow!(
+                        "character {:?} at position {} cannot be encoded",
+                        rest.chars().next().unwrap(),
+                        position
+                    )
+                })?;
+            if *code > 0xFF {
+                encoded.push((code >> 8) as u8);
+            }
+            encoded.push(*code as u8);
+            rest = &rest[piece.len()..];
+        }
+        encoded.push(0xFF);
+        Ok(encoded)
+    }
+
+    /// Parse a table in the `.tbl` format commonly used for ROM hacking:
+    /// one entry per line, written as the character code in hexadecimal,
+    /// an equals sign, and the text it represents (for example `0A=A` or
+    /// `F006=Digimon`).  Blank lines, and lines starting with `#` or `;`,
+    /// are ignored.
+    pub fn from_tbl(tbl: &str) -> anyhow::Result<Self> {
+        let mut table = Self::default();
+        for (i, line) in tbl.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty()
+                || line.starts_with('#')
+                || line.starts_with(';')
+            {
+                continue;
+            }
+            let (code, text) = line
+                .find('=')
+                .map(|delimiter| (&line[..delimiter], &line[delimiter + 1..]))
+                .ok_or_else(|| {
+                    anyhow!("line {}: expected \"hex=text\"", i + 1)
+                })?;
+            let code = u16::from_str_radix(code.trim(), 16).map_err(|_| {
+                anyhow!("line {}: invalid character code {:?}", i + 1, code)
+            })?;
+            if code > 0xFF && code >> 8 != 0xF0 {
+                return Err(anyhow!(
+                    "line {}: two-byte character codes must start with F0",
+                    i + 1
+                ));
+            }
+            if code == 0xFF || code == 0xF0 {
+                return Err(anyhow!(
+                    "line {}: character code {:02X} is reserved",
+                    i + 1,
+                    code
+                ));
+            }
+            table.insert(code, text);
+        }
+        Ok(table)
+    }
+
+    /// All entries of the table, in no particular order.
+    pub fn entries(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.entries.iter().map(|(code, text)| (*code, text.as_str()))
+    }
+
+    /// Look up the text represented by the given character code.
+    pub fn get(
+        &self,
+        code: u16,
+    ) -> Option<&str> {
+        self.entries.get(&code).map(String::as_str)
+    }
+
+    /// Add an entry to the table, replacing any existing entry for the same
+    /// character code.
+    pub fn insert<T>(
+        &mut self,
+        code: u16,
+        text: T,
+    ) where
+        T: Into<String>,
+    {
+        self.entries.insert(code, text.into());
+    }
+
+    /// Load a table from a `.tbl` file (see [`CharacterTable::from_tbl`]).
+    pub fn load_tbl<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let tbl = std::fs::read_to_string(path)
+            .context(format!("reading table file \"{}\"", path.display()))?;
+        Self::from_tbl(&tbl)
+            .context(format!("parsing table file \"{}\"", path.display()))
+    }
+
+    /// Add all entries of the other table to this one, replacing any
+    /// entries for the same character codes.
+    pub fn merge(
+        &mut self,
+        other: &Self,
+    ) {
+        for (code, text) in &other.entries {
+            self.entries.insert(*code, text.clone());
+        }
+    }
+
+    /// Decode a string in the game's text encoding, ending with `0xFF`.
+    pub fn parse_string(
+        &self,
+        mut raw: &[u8],
+    ) -> anyhow::Result<String> {
+        let mut value = String::new();
+        loop {
+            let (&first, rest) = raw
+                .split_first()
+                .ok_or_else(|| anyhow!("truncated character"))?;
+            raw = rest;
+            let encoding = match first {
+                0xFF => break,
+                0xF0 => {
+                    let (&second, rest) = raw
+                        .split_first()
+                        .ok_or_else(|| anyhow!("truncated character"))?;
+                    raw = rest;
+                    u16::from(first) << 8 | u16::from(second)
+                },
+                first => u16::from(first),
+            };
+            value.push_str(self.get(encoding).ok_or_else(|| {
+                anyhow!("illegal character 0x{:02X}", encoding)
+            })?);
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,46 @@
+use crate::pointers::parse_records;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// A treasure chest which may appear in a layout.
+///
+/// The two bytes following the coordinates describe what is inside the
+/// chest and how likely it is to appear.  Their exact encoding is not yet
+/// fully understood, so they are kept as-is.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Chest {
+    pub x: u8,
+    pub y: u8,
+
+    /// Identifies the item inside the chest.
+    pub item: u8,
+
+    /// How likely the chest is to spawn.
+    pub rate: u8,
+}
+
+impl Chest {
+    /// Number of bytes each chest occupies in a dungeon file.
+    pub const SIZE: usize = 4;
+
+    /// Parse the list of chests at the front of the given slice.  Each
+    /// chest is four bytes (`XX YY ITEM RATE`), and the list ends with
+    /// `0xFF`.
+    pub fn parse_list(raw: &[u8]) -> anyhow::Result<Vec<Self>> {
+        Ok(parse_records(raw, Self::SIZE)?
+            .map(|chest| Self {
+                x: chest[0],
+                y: chest[1],
+                item: chest[2],
+                rate: chest[3],
+            })
+            .collect())
+    }
+
+    /// Encode the chest as it appears in a dungeon file.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [self.x, self.y, self.item, self.rate]
+    }
+}
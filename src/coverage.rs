@@ -0,0 +1,139 @@
+use std::{
+    fmt::Write as _,
+    ops::Range,
+};
+
+/// A part of a dungeon file holding one of its structures.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Region {
+    /// The offsets of the bytes of the structure.
+    pub range: Range<usize>,
+
+    /// What the structure is, such as "floor 2 name" or "floor 1 layout 5
+    /// chests".  Layouts are described as part of the first floor which
+    /// uses them.
+    pub description: String,
+}
+
+/// A record of which bytes of a dungeon file were consumed while parsing
+/// it, built by
+/// [`Dungeon::parse_with_coverage`](crate::Dungeon::parse_with_coverage).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Coverage {
+    len: usize,
+    regions: Vec<Region>,
+    context: Vec<String>,
+}
+
+impl Coverage {
+    /// Number of bytes of the file which were consumed by the parser.
+    pub fn covered_len(&self) -> usize {
+        self.len - self.gaps().iter().map(|gap| gap.len()).sum::<usize>()
+    }
+
+    /// Number of bytes in the file.
+    pub fn file_len(&self) -> usize {
+        self.len
+    }
+
+    /// The ranges of bytes of the file which were not consumed by the
+    /// parser, in order.
+    pub fn gaps(&self) -> Vec<Range<usize>> {
+        let mut ranges = self
+            .regions
+            .iter()
+            .map(|region| region.range.clone())
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|range| range.start);
+        let mut gaps = Vec::new();
+        let mut next = 0;
+        for range in ranges {
+            if range.start > next {
+                gaps.push(next..range.start);
+            }
+            next = next.max(range.end);
+        }
+        if next < self.len {
+            gaps.push(next..self.len);
+        }
+        gaps
+    }
+
+    /// Start a record for a file of the given length.
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            ..Self::default()
+        }
+    }
+
+    /// Percentage of the bytes of the file which were consumed by the
+    /// parser.
+    pub fn percentage(&self) -> f64 {
+        if self.len == 0 {
+            100.0
+        } else {
+            self.covered_len() as f64 * 100.0 / self.len as f64
+        }
+    }
+
+    /// Record that the parser consumed the given range of bytes, holding
+    /// the structure with the given description.
+    pub(crate) fn record<D>(
+        &mut self,
+        range: Range<usize>,
+        description: D,
+    ) where
+        D: AsRef<str>,
+    {
+        let mut full_description = self.context.join(" ");
+        if !full_description.is_empty() {
+            full_description.push(' ');
+        }
+        full_description.push_str(description.as_ref());
+        self.regions.push(Region {
+            range,
+            description: full_description,
+        });
+    }
+
+    /// The regions of the file consumed by the parser, in the order they
+    /// were consumed.  Regions may overlap where structures are shared.
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Call the given function, describing all regions it records as part
+    /// of the given structure, such as "floor 2".
+    pub(crate) fn within<F, T>(
+        &mut self,
+        context: String,
+        f: F,
+    ) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        self.context.push(context);
+        let result = f(self);
+        self.context.pop();
+        result
+    }
+}
+
+/// Format the given bytes as a hex dump, sixteen bytes to a line, each line
+/// starting with the file offset of its first byte.  `start` is the file
+/// offset of the first of the given bytes.
+pub fn hex_dump(
+    raw: &[u8],
+    start: usize,
+) -> String {
+    let mut dump = String::new();
+    for (i, line) in raw.chunks(16).enumerate() {
+        let _ = write!(dump, "{:08X}:", start + i * 16);
+        for byte in line {
+            let _ = write!(dump, " {:02X}", byte);
+        }
+        dump.push('\n');
+    }
+    dump
+}
@@ -0,0 +1,85 @@
+use crate::pointers::{
+    deserialize_nibble,
+    parse_records,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::collections::BTreeMap;
+
+/// One of the two possible encounters at a [`DigimonSpawn`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Encounter {
+    /// The encounter group (upper four bits of the encounter byte).
+    #[serde(deserialize_with = "deserialize_nibble")]
+    pub group: u8,
+
+    /// The encounter chance (lower four bits of the encounter byte).
+    #[serde(deserialize_with = "deserialize_nibble")]
+    pub chance: u8,
+}
+
+impl Encounter {
+    /// Encode the encounter as it appears in a spawn record.
+    pub fn code(self) -> u8 {
+        self.group << 4 | (self.chance & 0x0F)
+    }
+}
+
+impl From<u8> for Encounter {
+    fn from(code: u8) -> Self {
+        Self {
+            group: code >> 4,
+            chance: code & 0x0F,
+        }
+    }
+}
+
+/// A location in a layout where wild Digimon may be encountered.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct DigimonSpawn {
+    pub x: u8,
+    pub y: u8,
+    pub encounters: [Encounter; 2],
+}
+
+impl DigimonSpawn {
+    /// Number of bytes each spawn point occupies in a dungeon file.
+    pub const SIZE: usize = 4;
+
+    /// Compute the effective probability (from 0.0 to 1.0) of each
+    /// encounter group appearing at this spawn point.  Each of the two
+    /// encounters is picked with equal chance, so for example encounter
+    /// bytes `11 44` give group 1 and group 4 each a probability of 0.5,
+    /// and `11 11` gives group 1 a probability of 1.0.
+    pub fn group_probabilities(&self) -> BTreeMap<u8, f64> {
+        let mut probabilities = BTreeMap::new();
+        for encounter in &self.encounters {
+            *probabilities.entry(encounter.group).or_insert(0.0) +=
+                1.0 / self.encounters.len() as f64;
+        }
+        probabilities
+    }
+
+    /// Parse the list of Digimon spawn points at the front of the given
+    /// slice.  Each spawn point is four bytes (`XX YY E1 E2`), and the list
+    /// ends with `0xFF`.
+    pub fn parse_list(raw: &[u8]) -> anyhow::Result<Vec<Self>> {
+        Ok(parse_records(raw, Self::SIZE)?
+            .map(|spawn| Self {
+                x: spawn[0],
+                y: spawn[1],
+                encounters: [
+                    Encounter::from(spawn[2]),
+                    Encounter::from(spawn[3]),
+                ],
+            })
+            .collect())
+    }
+
+    /// Encode the spawn point as it appears in a dungeon file.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [self.x, self.y, self.encounters[0].code(), self.encounters[1].code()]
+    }
+}
@@ -0,0 +1,573 @@
+use crate::{
+    dungeon::Dungeon,
+    sector,
+};
+use anyhow::{
+    anyhow,
+    Context as _,
+};
+use std::{
+    convert::TryFrom,
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Number of bytes of each sector stored in a raw disc image.
+pub const RAW_SECTOR_SIZE: usize = 2352;
+
+/// Number of bytes of user data in each sector holding files.
+pub const SECTOR_DATA_SIZE: usize = 2048;
+
+/// The twelve bytes which begin every raw CD sector.
+const SYNC: [u8; 12] =
+    [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// How the sectors of a disc image are stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SectorFormat {
+    /// Only the 2048 bytes of user data of each sector, as in an `.iso`
+    /// file.
+    Cooked,
+
+    /// Whole 2352-byte sectors, with sync pattern, header, and (for Mode 2,
+    /// as used by the PlayStation) subheader, error detection and error
+    /// correction codes, as in a `.bin` file described by a `.cue` sheet.
+    Raw,
+}
+
+impl SectorFormat {
+    /// Number of bytes of each sector stored in the image.
+    pub fn sector_size(self) -> usize {
+        match self {
+            SectorFormat::Cooked => SECTOR_DATA_SIZE,
+            SectorFormat::Raw => RAW_SECTOR_SIZE,
+        }
+    }
+}
+
+/// A file found in the ISO9660 file system of a disc.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiscFile {
+    /// Path of the file from the root directory, with directories separated
+    /// by `/` and without the `;1` version suffix, such as
+    /// `"DUNG/DUNG4000.BIN"`.
+    pub path: String,
+
+    /// The sector at which the file begins.
+    pub lba: u32,
+
+    /// Number of bytes in the file.
+    pub len: u32,
+
+    // The sector, and offset within it, of the directory record of the file.
+    record: (u32, usize),
+
+    // Number of sectors the file was found to take up.  See
+    // `DiscFile::sectors`.
+    sectors: usize,
+}
+
+impl DiscFile {
+    /// Number of sectors allocated to the file, which is as many bytes as
+    /// the file can grow to without moving.  This is the extent the file
+    /// took up when it was found on the disc, and stays the same in the file
+    /// returned by [`Disc::replace_file`] when the file shrinks.
+    pub fn sectors(&self) -> usize {
+        self.sectors
+    }
+
+    /// The name of the file, without the directories containing it.
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    /// Whether the file is one of the game's dungeon files
+    /// (`DUNGxxxx.BIN`).
+    pub fn is_dungeon(&self) -> bool {
+        let name = self.name();
+        name.len() == 12
+            && name.starts_with("DUNG")
+            && name.ends_with(".BIN")
+            && name[4..8].bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
+}
+
+/// A disc image of the game, from which files can be read through its
+/// ISO9660 file system.
+pub struct Disc<F> {
+    image: F,
+    format: SectorFormat,
+}
+
+impl Disc<File> {
+    /// Open the disc image at the given path.  This may be an `.iso` file,
+    /// a `.bin` file of raw sectors, or a `.cue` sheet, in which case the
+    /// first file named by the sheet is opened.
+    pub fn open<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = image_path(path.as_ref())?;
+        let image = File::open(&path)
+            .context(format!("opening disc image \"{}\"", path.display()))?;
+        Self::new(image)
+    }
+
+    /// Open the disc image at the given path (see [`Disc::open`]) so that
+    /// files can be replaced.
+    pub fn open_writable<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = image_path(path.as_ref())?;
+        let image =
+            OpenOptions::new().read(true).write(true).open(&path).context(
+                format!("opening disc image \"{}\"", path.display()),
+            )?;
+        Self::new(image)
+    }
+}
+
+impl<F> Disc<F>
+where
+    F: Read + Seek,
+{
+    /// Find the dungeon file with the given name (such as
+    /// `"DUNG4000.BIN"`), in any directory of the disc.
+    pub fn dungeon_file(
+        &mut self,
+        name: &str,
+    ) -> anyhow::Result<DiscFile> {
+        self.dungeon_files()?
+            .into_iter()
+            .find(|file| file.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("no dungeon file \"{}\" on disc", name))
+    }
+
+    /// List the dungeon files (`DUNGxxxx.BIN`) of the disc.
+    pub fn dungeon_files(&mut self) -> anyhow::Result<Vec<DiscFile>> {
+        Ok(self.files()?.into_iter().filter(DiscFile::is_dungeon).collect())
+    }
+
+    /// List every file of the disc, in the order of their directory
+    /// records, with the files of each directory following it.
+    pub fn files(&mut self) -> anyhow::Result<Vec<DiscFile>> {
+        let descriptors = self.volume_descriptors()?;
+        self.list_tree(&descriptors[0])
+    }
+
+    /// How the sectors of the disc image are stored.
+    pub fn format(&self) -> SectorFormat {
+        self.format
+    }
+
+    /// Take back the disc image.
+    pub fn into_inner(self) -> F {
+        self.image
+    }
+
+    /// Take the given disc image, detecting whether it holds raw sectors
+    /// from the sync pattern which begins every raw sector.
+    pub fn new(mut image: F) -> anyhow::Result<Self> {
+        let mut sync = [0; 12];
+        image.seek(SeekFrom::Start(0)).context("reading disc image")?;
+        let format = match image.read_exact(&mut sync) {
+            Ok(()) if sync == SYNC => SectorFormat::Raw,
+            _ => SectorFormat::Cooked,
+        };
+        Ok(Self {
+            image,
+            format,
+        })
+    }
+
+    // Gather the files of the directory with the given extent, and of all
+    // directories within it, into `files`.
+    fn list_directory(
+        &mut self,
+        prefix: String,
+        lba: u32,
+        len: u32,
+        files: &mut Vec<DiscFile>,
+        depth: usize,
+    ) -> anyhow::Result<()> {
+        // ISO9660 allows at most eight levels of directories, so anything
+        // deeper is a loop in a corrupt file system.
+        if depth > 8 {
+            return Err(anyhow!("directories nested too deeply"));
+        }
+        let sectors = (len as usize).div_ceil(SECTOR_DATA_SIZE);
+        let mut subdirectories = Vec::new();
+        for sector in 0..sectors {
+            let lba = lba + sector as u32;
+            let data = self
+                .read_sector(lba)
+                .context(format!("reading directory at sector {}", lba))?;
+            let mut offset = 0;
+            // A record never spans sectors; a zero length byte means the
+            // rest of the sector is unused.
+            while offset < data.len() && data[offset] != 0 {
+                let record_len = usize::from(data[offset]);
+                let record = data
+                    .get(offset..offset + record_len)
+                    .and_then(parse_record)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "bad directory record at sector {} offset {}",
+                            lba,
+                            offset
+                        )
+                    })?;
+                offset += record_len;
+                if record.name == "\0" || record.name == "\u{1}" {
+                    continue;
+                }
+                let record_offset = offset - record_len;
+                let path = format!("{}{}", prefix, record.name);
+                if record.is_directory {
+                    subdirectories.push((path, record.lba, record.len));
+                } else {
+                    files.push(DiscFile {
+                        path,
+                        lba: record.lba,
+                        len: record.len,
+                        record: (lba, record_offset),
+                        sectors: (record.len as usize)
+                            .div_ceil(SECTOR_DATA_SIZE),
+                    });
+                }
+            }
+        }
+        for (path, lba, len) in subdirectories {
+            self.list_directory(
+                format!("{}/", path),
+                lba,
+                len,
+                files,
+                depth + 1,
+            )?;
+        }
+        Ok(())
+    }
+
+    // List the files of the directory tree of the given volume descriptor.
+    fn list_tree(
+        &mut self,
+        descriptor: &[u8; SECTOR_DATA_SIZE],
+    ) -> anyhow::Result<Vec<DiscFile>> {
+        let root = parse_record(&descriptor[156..190])
+            .ok_or_else(|| anyhow!("bad root directory record"))?;
+        let mut files = Vec::new();
+        self.list_directory(String::new(), root.lba, root.len, &mut files, 0)?;
+        Ok(files)
+    }
+
+    /// Parse the given dungeon file of the disc.
+    pub fn load_dungeon(
+        &mut self,
+        file: &DiscFile,
+    ) -> anyhow::Result<Dungeon> {
+        let raw = self.read_file(file)?;
+        Dungeon::try_from(&raw).context(format!("parsing \"{}\"", file.path))
+    }
+
+    /// Read the contents of the given file of the disc.
+    pub fn read_file(
+        &mut self,
+        file: &DiscFile,
+    ) -> anyhow::Result<Vec<u8>> {
+        let len = file.len as usize;
+        // The length comes from the image, so the contents grow as sectors
+        // are read rather than trusting it up front.
+        let mut contents = Vec::new();
+        let mut lba = file.lba;
+        while contents.len() < len {
+            let data = self
+                .read_sector(lba)
+                .context(format!("reading \"{}\"", file.path))?;
+            let wanted = (len - contents.len()).min(data.len());
+            contents.extend_from_slice(&data[..wanted]);
+            lba += 1;
+        }
+        Ok(contents)
+    }
+
+    // Read the primary volume descriptor, followed by any supplementary
+    // volume descriptors (such as those of Joliet file names).
+    fn volume_descriptors(
+        &mut self
+    ) -> anyhow::Result<Vec<[u8; SECTOR_DATA_SIZE]>> {
+        let primary = self
+            .read_sector(16)
+            .context("reading primary volume descriptor")?;
+        if primary[0] != 1 || &primary[1..6] != b"CD001" {
+            return Err(anyhow!("no ISO9660 primary volume descriptor"));
+        }
+        let block_size = u16::from_le_bytes([primary[128], primary[129]]);
+        if usize::from(block_size) != SECTOR_DATA_SIZE {
+            return Err(anyhow!(
+                "unsupported logical block size {}",
+                block_size
+            ));
+        }
+        let mut descriptors = vec![primary];
+        for lba in 17..32 {
+            let descriptor =
+                self.read_sector(lba).context("reading volume descriptor")?;
+            if &descriptor[1..6] != b"CD001" || descriptor[0] == 255 {
+                break;
+            }
+            if descriptor[0] == 2 {
+                descriptors.push(descriptor);
+            }
+        }
+        Ok(descriptors)
+    }
+
+    /// Read the user data of the sector with the given logical block
+    /// address.
+    pub fn read_sector(
+        &mut self,
+        lba: u32,
+    ) -> anyhow::Result<[u8; SECTOR_DATA_SIZE]> {
+        let sector_size = self.format.sector_size() as u64;
+        let mut data = [0; SECTOR_DATA_SIZE];
+        match self.format {
+            SectorFormat::Cooked => {
+                self.image
+                    .seek(SeekFrom::Start(u64::from(lba) * sector_size))?;
+                self.image.read_exact(&mut data)?;
+            },
+            SectorFormat::Raw => {
+                let mut sector = [0; RAW_SECTOR_SIZE];
+                self.image
+                    .seek(SeekFrom::Start(u64::from(lba) * sector_size))?;
+                self.image.read_exact(&mut sector)?;
+                let offset = data_offset(&sector)?;
+                data.copy_from_slice(
+                    &sector[offset..offset + SECTOR_DATA_SIZE],
+                );
+            },
+        }
+        Ok(data)
+    }
+}
+
+impl<F> Disc<F>
+where
+    F: Read + Write + Seek,
+{
+    /// Replace the given dungeon file of the disc with the encoding of the
+    /// given dungeon (see [`Disc::replace_file`]).
+    pub fn replace_dungeon(
+        &mut self,
+        file: &DiscFile,
+        dungeon: &Dungeon,
+    ) -> anyhow::Result<DiscFile> {
+        self.replace_file(file, &dungeon.to_bytes())
+    }
+
+    /// Replace the contents of the given file of the disc, returning the
+    /// file as it now is.  The new contents are written in place, so they
+    /// must fit in the sectors allocated to the file (see
+    /// [`DiscFile::sectors`]); files are not moved, since the game may
+    /// locate them by sector rather than through the file system.  Unused
+    /// space in the sectors allocated to the file is filled with zeros.  The
+    /// length of the file is updated in its directory record, and in those
+    /// of any supplementary (such as Joliet) directory tree.  Nothing is
+    /// written unless every sector to be written can hold file data.
+    pub fn replace_file(
+        &mut self,
+        file: &DiscFile,
+        contents: &[u8],
+    ) -> anyhow::Result<DiscFile> {
+        let sectors = contents.len().div_ceil(SECTOR_DATA_SIZE);
+        if sectors > file.sectors() {
+            return Err(anyhow!(
+                "\"{}\" needs {} sectors but only {} are allocated to it",
+                file.path,
+                sectors,
+                file.sectors()
+            ));
+        }
+        let len = u32::try_from(contents.len())
+            .map_err(|_| anyhow!("\"{}\" is too large", file.path))?;
+        let mut records = vec![file.record];
+        for descriptor in &self.volume_descriptors()?[1..] {
+            records.extend(
+                self.list_tree(descriptor)?
+                    .iter()
+                    .filter(|other| other.lba == file.lba)
+                    .map(|other| other.record),
+            );
+        }
+        let targets = (0..file.sectors())
+            .map(|sector| file.lba + sector as u32)
+            .chain(records.iter().map(|&(record_lba, _)| record_lba));
+        for lba in targets {
+            self.check_writable(lba)
+                .context(format!("writing \"{}\"", file.path))?;
+        }
+        for sector in 0..file.sectors() {
+            let mut data = [0; SECTOR_DATA_SIZE];
+            let chunk = contents.chunks(SECTOR_DATA_SIZE).nth(sector);
+            if let Some(chunk) = chunk {
+                data[..chunk.len()].copy_from_slice(chunk);
+            }
+            self.write_sector(file.lba + sector as u32, &data)
+                .context(format!("writing \"{}\"", file.path))?;
+        }
+        for (record_lba, record_offset) in records {
+            let mut directory = self.read_sector(record_lba)?;
+            directory[record_offset + 10..record_offset + 14]
+                .copy_from_slice(&len.to_le_bytes());
+            directory[record_offset + 14..record_offset + 18]
+                .copy_from_slice(&len.to_be_bytes());
+            self.write_sector(record_lba, &directory).context(format!(
+                "updating directory record of \"{}\"",
+                file.path
+            ))?;
+        }
+        Ok(DiscFile {
+            len,
+            ..file.clone()
+        })
+    }
+
+    // Fail if the sector with the given logical block address cannot hold
+    // file data, so that it can be checked before anything is written.
+    fn check_writable(
+        &mut self,
+        lba: u32,
+    ) -> anyhow::Result<()> {
+        if self.format == SectorFormat::Raw {
+            let mut header = [0; 24];
+            self.image.seek(SeekFrom::Start(
+                u64::from(lba) * RAW_SECTOR_SIZE as u64,
+            ))?;
+            self.image.read_exact(&mut header)?;
+            check_form_1(&header, lba)?;
+        }
+        Ok(())
+    }
+
+    /// Overwrite the user data of the sector with the given logical block
+    /// address.  The header of a raw sector is kept, and its error detection
+    /// and correction codes recomputed.
+    pub fn write_sector(
+        &mut self,
+        lba: u32,
+        data: &[u8; SECTOR_DATA_SIZE],
+    ) -> anyhow::Result<()> {
+        let position = u64::from(lba) * self.format.sector_size() as u64;
+        match self.format {
+            SectorFormat::Cooked => {
+                self.image.seek(SeekFrom::Start(position))?;
+                self.image.write_all(data)?;
+            },
+            SectorFormat::Raw => {
+                let mut sector = [0; RAW_SECTOR_SIZE];
+                self.image.seek(SeekFrom::Start(position))?;
+                self.image.read_exact(&mut sector)?;
+                check_form_1(&sector, lba)?;
+                let offset = data_offset(&sector)?;
+                sector[offset..offset + SECTOR_DATA_SIZE].copy_from_slice(data);
+                sector::update_codes(&mut sector)?;
+                self.image.seek(SeekFrom::Start(position))?;
+                self.image.write_all(&sector)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+// The parts of a directory record which are needed to find files.
+struct DirectoryRecord {
+    lba: u32,
+    len: u32,
+    is_directory: bool,
+    name: String,
+}
+
+// Fail if the given raw sector is Mode 2 Form 2, which has no error
+// correction and so cannot hold files.
+fn check_form_1(
+    sector: &[u8],
+    lba: u32,
+) -> anyhow::Result<()> {
+    if sector[15] == 2 && sector[18] & 0x20 != 0 {
+        return Err(anyhow!(
+            "sector {} is Mode 2 Form 2, which cannot hold files",
+            lba
+        ));
+    }
+    Ok(())
+}
+
+// Find the offset of the user data in the given raw sector, which depends on
+// its mode.
+pub(crate) fn data_offset(sector: &[u8]) -> anyhow::Result<usize> {
+    match sector[15] {
+        1 => Ok(16),
+        2 => Ok(24),
+        mode => Err(anyhow!("unsupported sector mode {}", mode)),
+    }
+}
+
+// Find the image named by the given cue sheet, or the given image itself if
+// it is not a cue sheet.
+fn image_path(path: &Path) -> anyhow::Result<PathBuf> {
+    let is_cue = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("cue"));
+    if !is_cue {
+        return Ok(path.to_path_buf());
+    }
+    let sheet = std::fs::read_to_string(path)
+        .context(format!("reading cue sheet \"{}\"", path.display()))?;
+    let name = sheet
+        .lines()
+        .map(str::trim)
+        .find_map(|line| {
+            let rest = line.strip_prefix("FILE ")?;
+            match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next(),
+                None => rest.split_whitespace().next(),
+            }
+        })
+        .ok_or_else(|| {
+            anyhow!("no FILE command in cue sheet \"{}\"", path.display())
+        })?;
+    Ok(path.parent().unwrap_or_else(|| Path::new("")).join(name))
+}
+
+fn parse_record(raw: &[u8]) -> Option<DirectoryRecord> {
+    let name_len = usize::from(*raw.get(32)?);
+    let name = raw.get(33..33 + name_len)?;
+    let name = String::from_utf8_lossy(name);
+    let name = name.split(';').next().unwrap_or_default();
+    // Files without an extension are recorded with a trailing dot.
+    let name = name.strip_suffix('.').unwrap_or(name);
+    Some(DirectoryRecord {
+        lba: u32_le(&raw[2..6]),
+        len: u32_le(&raw[10..14]),
+        is_directory: raw[25] & 0x02 != 0,
+        name: name.to_string(),
+    })
+}
+
+fn u32_le(raw: &[u8]) -> u32 {
+    u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]])
+}
@@ -0,0 +1,261 @@
+use crate::{
+    coverage::Coverage,
+    floor::Floor,
+    layout::{
+        Layout,
+        LayoutId,
+    },
+    pointers::{
+        pad_to_alignment,
+        parse_ptr,
+        write_ptr,
+    },
+    validate::{
+        self,
+        Diagnostic,
+    },
+};
+use anyhow::Context as _;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::File,
+    io::Read as _,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// A whole dungeon, as stored in one of the game's `DUNGxxxx.BIN` files.
+///
+/// A dungeon can also be serialized, for example as JSON.  Floors refer to
+/// their layouts by index, just as [`Floor::layout_slots`] does, so layouts
+/// shared between floors stay shared.
+///
+/// Layouts can be edited through [`Dungeon::layout_mut`], after which the
+/// dungeon is marked as needing to be encoded again (see
+/// [`Dungeon::is_dirty`]).  Whether a dungeon is dirty plays no part in
+/// comparing or serializing it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "DungeonParts")]
+pub struct Dungeon {
+    floors: Vec<Floor>,
+    layouts: Vec<Layout>,
+
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Dungeon {
+    /// The floors of the dungeon, in the order the player visits them.
+    pub fn floors(&self) -> &[Floor] {
+        &self.floors
+    }
+
+    /// Assemble a dungeon from its floors and layouts, checking that every
+    /// floor can be written as part of it.
+    pub(crate) fn from_parts(
+        floors: Vec<Floor>,
+        layouts: Vec<Layout>,
+    ) -> anyhow::Result<Self> {
+        for (i, floor) in floors.iter().enumerate() {
+            floor.check(layouts.len()).context(format!("floor {}", i + 1))?;
+        }
+        Ok(Self {
+            floors,
+            layouts,
+            dirty: false,
+        })
+    }
+
+    /// Whether the dungeon may have been edited since it was parsed,
+    /// deserialized or last marked clean, and so needs to be encoded again.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Look up the layout with the given identifier, as found in the layout
+    /// slots of each floor.
+    pub fn layout(
+        &self,
+        id: LayoutId,
+    ) -> Option<&Layout> {
+        self.layouts.get(id.0)
+    }
+
+    /// Look up the layout with the given identifier for editing, marking the
+    /// dungeon as dirty.  Every floor using the layout sees the edits.
+    pub fn layout_mut(
+        &mut self,
+        id: LayoutId,
+    ) -> Option<&mut Layout> {
+        let layout = self.layouts.get_mut(id.0)?;
+        self.dirty = true;
+        Some(layout)
+    }
+
+    /// All distinct layouts of the dungeon, forming the pool of layouts from
+    /// which floors select theirs.  A layout may be used by more than one
+    /// floor, and more than once by the same floor.
+    pub fn layouts(&self) -> &[Layout] {
+        &self.layouts
+    }
+
+    /// Record that the dungeon has been encoded since it was last edited,
+    /// for example after writing the result of [`Dungeon::to_bytes`] to a
+    /// file.
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Parse a dungeon file, recording which bytes of it were consumed by
+    /// the parser.
+    pub fn parse_with_coverage(raw: &[u8]) -> anyhow::Result<(Self, Coverage)> {
+        let mut coverage = Coverage::new(raw.len());
+        let mut floors = Vec::new();
+        let mut layouts = Vec::new();
+        let mut layout_ids = HashMap::new();
+        let mut raw_ptrs = raw;
+        let mut i = 1;
+        loop {
+            let floor_ptr =
+                parse_ptr(raw_ptrs).context("parsing next floor pointer")?;
+            raw_ptrs = &raw_ptrs[4..];
+            if floor_ptr == 0 {
+                break;
+            }
+            floors.push(
+                coverage
+                    .within(format!("floor {}", i), |coverage| {
+                        Floor::new(
+                            raw,
+                            floor_ptr,
+                            &mut layout_ids,
+                            &mut layouts,
+                            coverage,
+                        )
+                    })
+                    .context(format!("parsing floor {}", i))?,
+            );
+            i += 1;
+        }
+        coverage.record(0..i * 4, "floor pointer table");
+        Ok((
+            Self {
+                floors,
+                layouts,
+                dirty: false,
+            },
+            coverage,
+        ))
+    }
+
+    /// Encode the dungeon as a `DUNGxxxx.BIN` file.
+    ///
+    /// The file begins with a table of pointers to the floor tables, ending
+    /// with a null pointer.  This is followed, for each floor in turn, by
+    /// each of its layouts not already written (floor plan, entity lists,
+    /// and layout pointer table), then the floor's name and floor table.
+    /// The file ends with the pool of entity lists which are not kept
+    /// alongside their layouts.  Every structure is aligned to four bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut raw = vec![0; (self.floors.len() + 1) * 4];
+        // A layout pointer of zero means the layout is not yet written, since
+        // nothing but the floor pointer table can be at the start of a file.
+        let mut layout_ptrs = vec![0; self.layouts.len()];
+        let mut pool = Vec::new();
+        for (i, floor) in self.floors.iter().enumerate() {
+            for id in floor.layout_slots() {
+                if layout_ptrs[id.0] == 0 {
+                    layout_ptrs[id.0] =
+                        self.layouts[id.0].write(&mut raw, &mut pool);
+                }
+            }
+            let floor_ptr = floor.write(&mut raw, &layout_ptrs);
+            write_ptr(&mut raw, i * 4, floor_ptr);
+        }
+        for pooled in pool {
+            let list_ptr = raw.len();
+            raw.extend(pooled.list);
+            pad_to_alignment(&mut raw);
+            write_ptr(&mut raw, pooled.ptr_offset, list_ptr);
+        }
+        raw
+    }
+
+    /// Check every layout of the dungeon for problems which would break it
+    /// in the game.  See [`validate::validate_dungeon`].
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate::validate_dungeon(self)
+    }
+}
+
+impl PartialEq for Dungeon {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.floors == other.floors && self.layouts == other.layouts
+    }
+}
+
+impl Eq for Dungeon {}
+
+// The fields of a deserialized dungeon, which must be checked for
+// consistency before they can make up a dungeon.
+#[derive(Deserialize)]
+struct DungeonParts {
+    floors: Vec<Floor>,
+    layouts: Vec<Layout>,
+}
+
+impl TryFrom<DungeonParts> for Dungeon {
+    type Error = anyhow::Error;
+
+    fn try_from(parts: DungeonParts) -> Result<Self, Self::Error> {
+        Self::from_parts(parts.floors, parts.layouts)
+    }
+}
+
+impl TryFrom<&[u8]> for Dungeon {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: &[u8]) -> Result<Self, Self::Error> {
+        Ok(Self::parse_with_coverage(raw)?.0)
+    }
+}
+
+impl TryFrom<&Vec<u8>> for Dungeon {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: &Vec<u8>) -> Result<Self, Self::Error> {
+        let raw: &[u8] = raw;
+        Self::try_from(raw)
+    }
+}
+
+impl TryFrom<&Path> for Dungeon {
+    type Error = anyhow::Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let mut file = File::open(path)
+            .context(format!("opening dungeon file \"{:?}\"", path))?;
+        let mut dungeon_raw = Vec::new();
+        file.read_to_end(&mut dungeon_raw).context("reading dungeon file")?;
+        Self::try_from(&dungeon_raw)
+    }
+}
+
+impl TryFrom<&PathBuf> for Dungeon {
+    type Error = anyhow::Error;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        let path: &Path = path;
+        Self::try_from(path)
+    }
+}
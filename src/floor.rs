@@ -0,0 +1,197 @@
+use crate::{
+    coverage::Coverage,
+    game_text::GameText,
+    layout::{
+        Layout,
+        LayoutId,
+    },
+    pointers::{
+        encode_ptr,
+        pad_to_alignment,
+        parse_ptr,
+        slice_from,
+    },
+};
+use anyhow::{
+    anyhow,
+    Context as _,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    convert::TryInto as _,
+};
+
+/// One floor of a dungeon.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Floor {
+    name: GameText,
+    // Second word of the floor table, whose meaning is not known.  See
+    // `Floor::unknown_word`.
+    unknown_word: u32,
+    layout_slots: [LayoutId; 8],
+    // Rest of the floor table following the layout pointers, whose meaning
+    // is not known.
+    unknown_data: Vec<u8>,
+}
+
+impl Floor {
+    /// Number of bytes the table of a floor occupies in a dungeon file.
+    pub const TABLE_SIZE: usize = 124;
+
+    /// Check that the floor can be written as part of a dungeon with the
+    /// given number of layouts.
+    pub(crate) fn check(
+        &self,
+        num_layouts: usize,
+    ) -> anyhow::Result<()> {
+        if let Some(id) =
+            self.layout_slots.iter().find(|&&id| id.0 >= num_layouts)
+        {
+            return Err(anyhow!("no layout with index {}", id.0));
+        }
+        if self.unknown_data.len() != Self::TABLE_SIZE - 40 {
+            return Err(anyhow!(
+                "expected {} bytes of unknown data but found {}",
+                Self::TABLE_SIZE - 40,
+                self.unknown_data.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Assemble a floor from its name, layout slots, and the values of the
+    /// parts of its table whose meaning is not known.
+    pub(crate) fn from_parts(
+        name: GameText,
+        unknown_word: u32,
+        layout_slots: [LayoutId; 8],
+        unknown_data: Vec<u8>,
+    ) -> Self {
+        Self {
+            name,
+            unknown_word,
+            layout_slots,
+            unknown_data,
+        }
+    }
+
+    /// The probability of each distinct layout of the floor being selected
+    /// when the floor is entered.  Since the game selects one of the eight
+    /// layout slots at random, a layout in more than one slot is more likely
+    /// to be selected.
+    pub fn layout_probabilities(&self) -> BTreeMap<LayoutId, f64> {
+        let mut probabilities = BTreeMap::new();
+        for &id in &self.layout_slots {
+            *probabilities.entry(id).or_insert(0.0) +=
+                1.0 / self.layout_slots.len() as f64;
+        }
+        probabilities
+    }
+
+    /// The eight layouts from which the game selects one at random each
+    /// time the floor is entered.  The same layout may be in more than one
+    /// slot.
+    pub fn layout_slots(&self) -> &[LayoutId; 8] {
+        &self.layout_slots
+    }
+
+    /// The name of the floor, as shown to the player.
+    pub fn name(&self) -> &GameText {
+        &self.name
+    }
+
+    /// The part of the floor table following the layout pointers, whose
+    /// meaning is not known.
+    pub(crate) fn unknown_data(&self) -> &[u8] {
+        &self.unknown_data
+    }
+
+    /// The second word of the floor table, whose meaning is not known.  It
+    /// is zero on most floors; on the last floor of some dungeons it holds
+    /// a small value which does not appear to be a pointer (see
+    /// [`analysis::floor_word_report`](crate::analysis::floor_word_report)).
+    pub fn unknown_word(&self) -> u32 {
+        self.unknown_word
+    }
+
+    /// Parse the floor table at `table_ptr`.  Layouts not already parsed
+    /// (as recorded in `layout_ids`, which maps layout table pointers to
+    /// the layouts in `layouts`) are parsed and added to `layouts`.  The
+    /// bytes consumed are recorded in `coverage`.
+    pub fn new(
+        raw: &[u8],
+        table_ptr: usize,
+        layout_ids: &mut HashMap<usize, LayoutId>,
+        layouts: &mut Vec<Layout>,
+        coverage: &mut Coverage,
+    ) -> anyhow::Result<Self> {
+        if raw.len() < table_ptr + Self::TABLE_SIZE {
+            return Err(anyhow!("truncated floor table"));
+        }
+        let name_ptr =
+            parse_ptr(&raw[table_ptr..]).context("parsing name pointer")?;
+        let name = GameText::parse(slice_from(raw, name_ptr)?)
+            .context("parsing name")?;
+        coverage.record(name_ptr..name_ptr + name.to_bytes().len(), "name");
+        // The rest of the table is not understood, so is left as a gap.
+        coverage.record(table_ptr..table_ptr + 40, "table");
+        let unknown_word =
+            u32::from_le_bytes(raw[table_ptr + 4..table_ptr + 8].try_into()?);
+        let mut next_layout_ptr_offset = &raw[table_ptr + 8..];
+        let mut layout_slots = [LayoutId(0); 8];
+        for (i, layout_slot) in layout_slots.iter_mut().enumerate() {
+            let layout_ptr = parse_ptr(next_layout_ptr_offset)
+                .context("parsing layout pointer")?;
+            next_layout_ptr_offset = &next_layout_ptr_offset[4..];
+            *layout_slot = if let Some(&id) = layout_ids.get(&layout_ptr) {
+                id
+            } else {
+                let id = LayoutId(layouts.len());
+                let layout = coverage
+                    .within(format!("layout {}", id.0 + 1), |coverage| {
+                        Layout::new(raw, layout_ptr, coverage)
+                    })
+                    .context(format!("parsing layout {}", i + 1))?;
+                layouts.push(layout);
+                layout_ids.insert(layout_ptr, id);
+                id
+            };
+        }
+        let unknown_data =
+            raw[table_ptr + 40..table_ptr + Self::TABLE_SIZE].to_vec();
+        Ok(Self {
+            name,
+            unknown_word,
+            layout_slots,
+            unknown_data,
+        })
+    }
+
+    /// Append the name and table of the floor to the given dungeon file
+    /// being built, returning the offset of the table.  `layout_ptrs` gives
+    /// the offset of the pointer table of each layout of the dungeon.
+    pub(crate) fn write(
+        &self,
+        raw: &mut Vec<u8>,
+        layout_ptrs: &[usize],
+    ) -> usize {
+        let name_ptr = raw.len();
+        raw.extend(self.name.to_bytes());
+        pad_to_alignment(raw);
+        let table_ptr = raw.len();
+        raw.extend_from_slice(&encode_ptr(name_ptr));
+        raw.extend_from_slice(&self.unknown_word.to_le_bytes());
+        for id in &self.layout_slots {
+            raw.extend_from_slice(&encode_ptr(layout_ptrs[id.0]));
+        }
+        raw.extend_from_slice(&self.unknown_data);
+        table_ptr
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    ascii_map::{
+        tile_from_glyph,
+        tile_glyph,
+    },
+    pointers::deserialize_nibble,
+};
+use anyhow::anyhow;
+use serde::{
+    de::Error as _,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use std::fmt::Display;
+
+/// The types of tile which make up a floor plan.  Each byte of a floor plan
+/// holds two tiles: the left tile in its lower four bits, and the right
+/// tile in its upper four bits.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Tile {
+    Room,
+    Corridor,
+    Water,
+    Fire,
+    Nature,
+    Machine,
+    Dark,
+    Empty,
+
+    /// A tile code whose meaning is not known (or which is an alternate
+    /// code for one of the known tiles), kept so that the floor plan can be
+    /// encoded again exactly as it was.  This is never one of the codes of
+    /// the known tiles.
+    Unknown(#[serde(deserialize_with = "deserialize_unknown_tile")] u8),
+}
+
+impl Tile {
+    /// Whether the tile is empty, so the player cannot walk on it.  As well
+    /// as [`Tile::Empty`], this is true of the unknown code `0x9`, which the
+    /// format notes also list as empty.
+    pub fn is_empty(self) -> bool {
+        matches!(self, Tile::Empty | Tile::Unknown(0x9))
+    }
+}
+
+impl From<u8> for Tile {
+    fn from(code: u8) -> Self {
+        match code {
+            0x0 => Self::Room,
+            0x1 => Self::Corridor,
+            0x2 => Self::Water,
+            0x3 => Self::Fire,
+            0x4 => Self::Nature,
+            0x5 => Self::Machine,
+            0x6 => Self::Dark,
+            0x8 => Self::Empty,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+// Deserialize the code of a `Tile::Unknown`, rejecting the codes of the known
+// tiles, which would not parse back as unknown.
+fn deserialize_unknown_tile<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let code = deserialize_nibble(deserializer)?;
+    match Tile::from(code) {
+        Tile::Unknown(_) => Ok(code),
+        tile => Err(D::Error::custom(format!(
+            "tile code {} is {:?}, not unknown",
+            code, tile
+        ))),
+    }
+}
+
+impl From<Tile> for u8 {
+    fn from(tile: Tile) -> Self {
+        match tile {
+            Tile::Room => 0x0,
+            Tile::Corridor => 0x1,
+            Tile::Water => 0x2,
+            Tile::Fire => 0x3,
+            Tile::Nature => 0x4,
+            Tile::Machine => 0x5,
+            Tile::Dark => 0x6,
+            Tile::Empty => 0x8,
+            Tile::Unknown(code) => code,
+        }
+    }
+}
+
+/// The layout of the tiles which make up a floor, as it appears to the
+/// player.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FloorPlan {
+    // 48 rows, 64 columns
+    tiles: [[Tile; FloorPlan::WIDTH]; FloorPlan::HEIGHT],
+}
+
+impl FloorPlan {
+    /// Number of rows of tiles in a floor plan.
+    pub const HEIGHT: usize = 48;
+    /// Number of bytes a floor plan occupies in a dungeon file.
+    pub const SIZE: usize = Self::WIDTH * Self::HEIGHT / 2;
+    /// Number of columns of tiles in a floor plan.
+    pub const WIDTH: usize = 64;
+
+    /// Build a floor plan from its rows of tiles, each drawn one character
+    /// per tile as by [`tile_glyph`].  This is the inverse of
+    /// [`FloorPlan::rows`].
+    pub fn from_rows<S>(rows: &[S]) -> anyhow::Result<Self>
+    where
+        S: AsRef<str>,
+    {
+        if rows.len() != Self::HEIGHT {
+            return Err(anyhow!(
+                "expected {} rows of tiles but found {}",
+                Self::HEIGHT,
+                rows.len()
+            ));
+        }
+        let mut tiles = [[Tile::Empty; Self::WIDTH]; Self::HEIGHT];
+        for (y, (row, glyphs)) in tiles.iter_mut().zip(rows).enumerate() {
+            let glyphs = glyphs.as_ref();
+            if glyphs.chars().count() != Self::WIDTH {
+                return Err(anyhow!(
+                    "row {} has {} tiles rather than {}",
+                    y,
+                    glyphs.chars().count(),
+                    Self::WIDTH
+                ));
+            }
+            for (x, (tile, glyph)) in
+                row.iter_mut().zip(glyphs.chars()).enumerate()
+            {
+                *tile = tile_from_glyph(glyph).ok_or_else(|| {
+                    anyhow!("unknown tile {:?} at ({}, {})", glyph, x, y)
+                })?;
+            }
+        }
+        Ok(Self {
+            tiles,
+        })
+    }
+
+    pub fn new(raw: &[u8]) -> anyhow::Result<Self> {
+        if raw.len() < Self::SIZE {
+            return Err(anyhow!("truncated floor plan"));
+        }
+        let mut tiles = [[Tile::Empty; Self::WIDTH]; Self::HEIGHT];
+        for (row, raw_row) in
+            tiles.iter_mut().zip(raw.chunks_exact(Self::WIDTH / 2))
+        {
+            for (pair, byte) in row.chunks_exact_mut(2).zip(raw_row) {
+                pair[0] = Tile::from(byte & 0x0F);
+                pair[1] = Tile::from(byte >> 4);
+            }
+        }
+        Ok(Self {
+            tiles,
+        })
+    }
+
+    /// The rows of tiles of the floor plan, from top to bottom, each drawn
+    /// one character per tile as by [`tile_glyph`].
+    pub fn rows(&self) -> Vec<String> {
+        self.tiles
+            .iter()
+            .map(|row| row.iter().copied().map(tile_glyph).collect())
+            .collect()
+    }
+
+    /// Change the tile at the given column (`x`) and row (`y`), failing if
+    /// the coordinates are outside the floor plan.
+    pub fn set_tile(
+        &mut self,
+        x: usize,
+        y: usize,
+        tile: Tile,
+    ) -> anyhow::Result<()> {
+        let cell =
+            self.tiles.get_mut(y).and_then(|row| row.get_mut(x)).ok_or_else(
+                || {
+                    anyhow!(
+                        "({}, {}) is outside the {}x{} floor plan",
+                        x,
+                        y,
+                        Self::WIDTH,
+                        Self::HEIGHT
+                    )
+                },
+            )?;
+        *cell = tile;
+        Ok(())
+    }
+
+    /// Look up the tile at the given column (`x`) and row (`y`), or `None`
+    /// if the coordinates are outside the floor plan.
+    pub fn tile(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> Option<Tile> {
+        self.tiles.get(y).and_then(|row| row.get(x)).copied()
+    }
+
+    /// Encode the floor plan as it appears in a dungeon file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.tiles
+            .iter()
+            .flat_map(|row| {
+                row.chunks_exact(2)
+                    .map(|pair| u8::from(pair[1]) << 4 | u8::from(pair[0]))
+            })
+            .collect()
+    }
+}
+
+impl Display for FloorPlan {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        for row in self.to_bytes().chunks_exact(Self::WIDTH / 2) {
+            for (x, byte) in row.iter().enumerate() {
+                if x != 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{:02X}", byte)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// A floor plan is stored as its rows of tiles (see [`FloorPlan::rows`]),
+/// which is far easier to read and edit than the tiles themselves.
+impl Serialize for FloorPlan {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.rows().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FloorPlan {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        Self::from_rows(&rows).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,415 @@
+use crate::character_table::CharacterTable;
+use anyhow::anyhow;
+use serde::{
+    de::Error as _,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use std::{
+    cell::RefCell,
+    fmt::Display,
+};
+
+/// Codes in game text which control how the text is presented rather than
+/// representing characters.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ControlCode {
+    /// `0xFB`, shown as `<X>`.
+    X,
+
+    /// `0xFC`, which continues the text in a new text box.
+    NewBox,
+
+    /// `0xFE`, which starts a new line.
+    Enter,
+}
+
+impl ControlCode {
+    /// The byte which encodes the control code.
+    pub fn code(self) -> u8 {
+        match self {
+            ControlCode::X => 0xFB,
+            ControlCode::NewBox => 0xFC,
+            ControlCode::Enter => 0xFE,
+        }
+    }
+
+    /// Look up the control code encoded by the given byte, if any.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0xFB => Some(ControlCode::X),
+            0xFC => Some(ControlCode::NewBox),
+            0xFE => Some(ControlCode::Enter),
+            _ => None,
+        }
+    }
+}
+
+/// One piece of game text.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Segment {
+    /// A single-byte character code found in the character map.
+    Glyph(u8),
+
+    /// A two-byte dictionary word code (`0xF0xx`), holding the second byte.
+    Word(u8),
+
+    /// A control code.
+    Control(ControlCode),
+
+    /// A single-byte character code not found in the character map.
+    Unknown(u8),
+}
+
+impl Segment {
+    /// The character code of the segment, as used in the character map.
+    /// Dictionary words have codes `0xF000`-`0xF0FF`.
+    pub fn code(self) -> u16 {
+        match self {
+            Segment::Glyph(code) | Segment::Unknown(code) => u16::from(code),
+            Segment::Word(code) => 0xF000 | u16::from(code),
+            Segment::Control(control) => u16::from(control.code()),
+        }
+    }
+
+    fn write(
+        self,
+        f: &mut std::fmt::Formatter<'_>,
+        table: &CharacterTable,
+    ) -> std::fmt::Result {
+        if let Some(text) = table.get(self.code()) {
+            f.write_str(text)
+        } else {
+            write!(f, "<0x{:02X}>", self.code())
+        }
+    }
+}
+
+impl Display for Segment {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        self.write(f, CharacterTable::builtin())
+    }
+}
+
+/// A string of game text, kept as the sequence of codes which encode it so
+/// that it can be encoded again exactly as it was, even if it contains
+/// codes not found in the character map.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GameText {
+    segments: Vec<Segment>,
+}
+
+impl GameText {
+    /// Show the text as decoded using the given character table rather than
+    /// the built-in one.
+    pub fn display_with<'a>(
+        &'a self,
+        table: &'a CharacterTable,
+    ) -> GameTextDisplay<'a> {
+        GameTextDisplay {
+            text: self,
+            table,
+        }
+    }
+
+    /// Encode the given text using the built-in character table (see
+    /// [`CharacterTable::encode_string`]).
+    pub fn encode(text: &str) -> anyhow::Result<Self> {
+        Self::encode_with(text, CharacterTable::builtin())
+    }
+
+    /// Encode the given text using the given character table (see
+    /// [`CharacterTable::encode_string`]).
+    pub fn encode_with(
+        text: &str,
+        table: &CharacterTable,
+    ) -> anyhow::Result<Self> {
+        Self::parse_with(&table.encode_string(text)?, table)
+    }
+
+    /// Read text written by [`GameText::to_exact_string`].  Unlike
+    /// [`GameText::encode`], dictionary words are only used where they are
+    /// written in braces, so text can be encoded exactly as intended.
+    pub fn from_exact_string(text: &str) -> anyhow::Result<Self> {
+        Self::from_exact_string_with(text, CharacterTable::builtin())
+    }
+
+    /// Read text written by [`GameText::to_exact_string_with`] using the
+    /// given character table.
+    pub fn from_exact_string_with(
+        text: &str,
+        table: &CharacterTable,
+    ) -> anyhow::Result<Self> {
+        let mut encoded = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let (code, len) =
+                read_exact_code(rest, text.len() - rest.len(), table)?;
+            if code > 0xFF {
+                encoded.push((code >> 8) as u8);
+            }
+            encoded.push(code as u8);
+            rest = &rest[len..];
+        }
+        encoded.push(0xFF);
+        Self::parse(&encoded)
+    }
+
+    /// Parse game text from the front of the given slice, up to the `0xFF`
+    /// which ends it, using the built-in character table to tell known
+    /// glyphs from unknown ones.
+    pub fn parse(raw: &[u8]) -> anyhow::Result<Self> {
+        Self::parse_with(raw, CharacterTable::builtin())
+    }
+
+    /// Parse game text from the front of the given slice, up to the `0xFF`
+    /// which ends it, using the given character table to tell known glyphs
+    /// from unknown ones.
+    pub fn parse_with(
+        mut raw: &[u8],
+        table: &CharacterTable,
+    ) -> anyhow::Result<Self> {
+        let mut segments = Vec::new();
+        loop {
+            let (&first, rest) =
+                raw.split_first().ok_or_else(|| anyhow!("truncated string"))?;
+            raw = rest;
+            let segment = match first {
+                0xFF => break,
+                0xF0 => {
+                    let (&second, rest) = raw
+                        .split_first()
+                        .ok_or_else(|| anyhow!("truncated character"))?;
+                    raw = rest;
+                    Segment::Word(second)
+                },
+                code => ControlCode::from_code(code).map_or_else(
+                    || {
+                        if table.get(u16::from(code)).is_some() {
+                            Segment::Glyph(code)
+                        } else {
+                            Segment::Unknown(code)
+                        }
+                    },
+                    Segment::Control,
+                ),
+            };
+            segments.push(segment);
+        }
+        Ok(Self {
+            segments,
+        })
+    }
+
+    /// The pieces which make up the text.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Write the text so that [`GameText::from_exact_string`] reads it back
+    /// exactly.  This is the decoded text, except that dictionary words are
+    /// written in braces, such as `{Domain}`, and codes which would not read
+    /// back as themselves are written in hex, such as `<0x7F>`.  Those are
+    /// codes missing from the character table, codes which decode to
+    /// nothing, and codes whose text would be read together with the text
+    /// after it as a different code.
+    pub fn to_exact_string(&self) -> String {
+        self.to_exact_string_with(CharacterTable::builtin())
+    }
+
+    /// Write the text so that [`GameText::from_exact_string_with`] reads it
+    /// back exactly using the given character table.
+    pub fn to_exact_string_with(
+        &self,
+        table: &CharacterTable,
+    ) -> String {
+        // Whether a code is read back correctly depends on the text after
+        // it, so the text is written from the end.
+        let mut exact = String::new();
+        for segment in self.segments.iter().rev() {
+            let code = segment.code();
+            let written = match table.get(code) {
+                Some(word) if code > 0xFF => format!("{{{}}}", word),
+                Some(text) => String::from(text),
+                None => String::new(),
+            };
+            let len = written.len();
+            let candidate = written + &exact;
+            exact = if len > 0
+                && read_exact_code(&candidate, 0, table).ok()
+                    == Some((code, len))
+            {
+                candidate
+            } else {
+                format!("<0x{:02X}>{}", code, exact)
+            };
+        }
+        exact
+    }
+
+    /// Encode the text, ending with `0xFF`, exactly as it was parsed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for segment in &self.segments {
+            let code = segment.code();
+            if code > 0xFF {
+                encoded.push((code >> 8) as u8);
+            }
+            encoded.push(code as u8);
+        }
+        encoded.push(0xFF);
+        encoded
+    }
+
+    /// Call the given function with game text serialized and deserialized
+    /// using the given character table, rather than the built-in one, on
+    /// this thread.  Serde gives no other way to pass the table to the
+    /// text of a [`Dungeon`](crate::Dungeon) being serialized.
+    pub fn with_serde_table<F, T>(
+        table: &CharacterTable,
+        f: F,
+    ) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let previous =
+            SERDE_TABLE.with(|current| current.replace(Some(table.clone())));
+        let result = f();
+        SERDE_TABLE.with(|current| current.replace(previous));
+        result
+    }
+}
+
+impl Display for GameText {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        self.display_with(CharacterTable::builtin()).fmt(f)
+    }
+}
+
+// Read the code at the front of text written by `to_exact_string`, which is
+// at the given position in the whole text, returning the code and the length
+// of the text it was read from.
+fn read_exact_code(
+    rest: &str,
+    position: usize,
+    table: &CharacterTable,
+) -> anyhow::Result<(u16, usize)> {
+    if rest.starts_with('{') {
+        let end = rest.find('}').ok_or_else(|| {
+            anyhow!("unterminated word at position {}", position)
+        })?;
+        let word = &rest[1..end];
+        let code = table
+            .entries()
+            .filter(|(code, text)| *code > 0xFF && *text == word)
+            .map(|(code, _)| code)
+            .min()
+            .ok_or_else(|| anyhow!("unknown word {:?}", word))?;
+        Ok((code, end + 1))
+    } else if rest.starts_with("<0x") {
+        let end = rest.find('>').ok_or_else(|| {
+            anyhow!("unterminated code at position {}", position)
+        })?;
+        let code = u16::from_str_radix(&rest[3..end], 16)
+            .ok()
+            .filter(|code| {
+                // `0xFF` would end the text, and `0xF0` on its own would
+                // take the next code as the second byte of a word.
+                (end == 5 && *code != 0xFF && *code != 0xF0)
+                    || (end == 7 && code >> 8 == 0xF0)
+            })
+            .ok_or_else(|| anyhow!("bad code {:?}", &rest[..=end]))?;
+        Ok((code, end + 1))
+    } else {
+        table
+            .entries()
+            .filter(|(code, piece)| {
+                *code <= 0xFF && !piece.is_empty() && rest.starts_with(piece)
+            })
+            .max_by_key(|(code, piece)| (piece.len(), std::cmp::Reverse(*code)))
+            .map(|(code, piece)| (code, piece.len()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "character {:?} at position {} cannot be encoded",
+                    rest.chars().next().unwrap_or_default(),
+                    position
+                )
+            })
+    }
+}
+
+thread_local! {
+    // The character table used to serialize and deserialize game text, if
+    // not the built-in one.  See `GameText::with_serde_table`.
+    static SERDE_TABLE: RefCell<Option<CharacterTable>> =
+        const { RefCell::new(None) };
+}
+
+/// Game text is stored in the form written by [`GameText::to_exact_string`]
+/// so that it reads back exactly as it was.  See also
+/// [`GameText::with_serde_table`].
+impl Serialize for GameText {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let text = SERDE_TABLE.with(|table| match &*table.borrow() {
+            Some(table) => self.to_exact_string_with(table),
+            None => self.to_exact_string(),
+        });
+        serializer.serialize_str(&text)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameText {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        SERDE_TABLE
+            .with(|table| match &*table.borrow() {
+                Some(table) => Self::from_exact_string_with(&text, table),
+                None => Self::from_exact_string(&text),
+            })
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Shows [`GameText`] as decoded using a particular character table.  See
+/// [`GameText::display_with`].
+pub struct GameTextDisplay<'a> {
+    text: &'a GameText,
+    table: &'a CharacterTable,
+}
+
+impl<'a> Display for GameTextDisplay<'a> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        for segment in &self.text.segments {
+            segment.write(f, self.table)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<Segment>> for GameText {
+    fn from(segments: Vec<Segment>) -> Self {
+        Self {
+            segments,
+        }
+    }
+}
@@ -0,0 +1,519 @@
+use crate::{
+    ascii_map,
+    chest::Chest,
+    coverage::Coverage,
+    digimon_spawn::{
+        DigimonSpawn,
+        Encounter,
+    },
+    floor_plan::FloorPlan,
+    pathfinding::{
+        self,
+        SpawnReachability,
+    },
+    png_map,
+    pointers::{
+        encode_list,
+        encode_ptr,
+        pad_to_alignment,
+        parse_ptr,
+        slice_from,
+    },
+    trap::{
+        Trap,
+        TrapKind,
+    },
+    validate::{
+        self,
+        Diagnostic,
+    },
+    warp::{
+        Warp,
+        WarpKind,
+    },
+};
+use anyhow::{
+    anyhow,
+    Context as _,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Where one of the entity lists of a layout is stored in a dungeon file.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ListPlacement {
+    /// Between the floor plan and the pointer table of the layout.
+    Inline,
+
+    /// In the pool of lists following the last floor table of the dungeon.
+    /// The game's files mostly keep lists of fewer than two entries there.
+    Pooled,
+}
+
+/// Where each of the entity lists of a layout is stored in a dungeon file.
+/// This has no effect on the game, but is kept so that a dungeon can be
+/// written back exactly as it was read.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ListPlacements {
+    pub warps: ListPlacement,
+    pub chests: ListPlacement,
+    pub traps: ListPlacement,
+    pub digimon: ListPlacement,
+}
+
+/// Identifies one of the layouts in the pool of layouts of a dungeon, by its
+/// index into [`Dungeon::layouts`](crate::Dungeon::layouts).  Floors refer to
+/// their layouts by identifier, so that floors can share layouts.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(transparent)]
+pub struct LayoutId(pub usize);
+
+/// An entity list to be written to the pool of lists of a dungeon file,
+/// along with the offset of the pointer which should point to it.
+pub(crate) struct PooledList {
+    pub ptr_offset: usize,
+    pub list: Vec<u8>,
+}
+
+// Check that the given coordinates are inside the floor plan.
+fn check_position(
+    x: u8,
+    y: u8,
+) -> anyhow::Result<()> {
+    if usize::from(x) < FloorPlan::WIDTH && usize::from(y) < FloorPlan::HEIGHT {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "({}, {}) is outside the {}x{} floor plan",
+            x,
+            y,
+            FloorPlan::WIDTH,
+            FloorPlan::HEIGHT
+        ))
+    }
+}
+
+// Look up the entity at the given index of the given list, where `what`
+// names the kind of entity for the error if there is none.
+fn entity_mut<'a, T>(
+    list: &'a mut [T],
+    index: usize,
+    what: &str,
+) -> anyhow::Result<&'a mut T> {
+    let len = list.len();
+    list.get_mut(index)
+        .ok_or_else(|| anyhow!("no {} {} (layout has {})", what, index, len))
+}
+
+/// One of the possible arrangements of a floor of a dungeon.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Layout {
+    floor_plan: FloorPlan,
+    warps: Vec<Warp>,
+    chests: Vec<Chest>,
+    traps: Vec<Trap>,
+    digimon: Vec<DigimonSpawn>,
+    list_placements: ListPlacements,
+}
+
+impl Layout {
+    /// Number of bytes the pointer table of a layout occupies in a dungeon
+    /// file.
+    pub const TABLE_SIZE: usize = 20;
+
+    /// Add a treasure chest at the given coordinates, returning its index.
+    pub fn add_chest(
+        &mut self,
+        x: u8,
+        y: u8,
+        item: u8,
+        rate: u8,
+    ) -> anyhow::Result<usize> {
+        check_position(x, y)?;
+        self.chests.push(Chest {
+            x,
+            y,
+            item,
+            rate,
+        });
+        Ok(self.chests.len() - 1)
+    }
+
+    /// Add a Digimon encounter point at the given coordinates, returning its
+    /// index.
+    pub fn add_digimon(
+        &mut self,
+        x: u8,
+        y: u8,
+        encounters: [Encounter; 2],
+    ) -> anyhow::Result<usize> {
+        check_position(x, y)?;
+        self.digimon.push(DigimonSpawn {
+            x,
+            y,
+            encounters,
+        });
+        Ok(self.digimon.len() - 1)
+    }
+
+    /// Add a trap at the given coordinates, returning its index.  The last
+    /// two bytes of the trap, whose meaning is not known, are zero, as they
+    /// are for every trap in the shipped dungeons.
+    pub fn add_trap(
+        &mut self,
+        x: u8,
+        y: u8,
+        slots: [Option<TrapKind>; 4],
+    ) -> anyhow::Result<usize> {
+        check_position(x, y)?;
+        self.traps.push(Trap {
+            x,
+            y,
+            slots,
+            trailer: [0, 0],
+        });
+        Ok(self.traps.len() - 1)
+    }
+
+    /// Add a warp at the given coordinates, returning its index.
+    pub fn add_warp(
+        &mut self,
+        x: u8,
+        y: u8,
+        kind: WarpKind,
+    ) -> anyhow::Result<usize> {
+        check_position(x, y)?;
+        self.warps.push(Warp {
+            x,
+            y,
+            kind,
+        });
+        Ok(self.warps.len() - 1)
+    }
+
+    /// The treasure chests which may appear in the layout.
+    pub fn chests(&self) -> &[Chest] {
+        &self.chests
+    }
+
+    /// The places in the layout where wild Digimon may be encountered.
+    pub fn digimon(&self) -> &[DigimonSpawn] {
+        &self.digimon
+    }
+
+    /// The arrangement of tiles making up the layout.
+    pub fn floor_plan(&self) -> &FloorPlan {
+        &self.floor_plan
+    }
+
+    /// The arrangement of tiles making up the layout, for editing.
+    pub fn floor_plan_mut(&mut self) -> &mut FloorPlan {
+        &mut self.floor_plan
+    }
+
+    /// Assemble a layout from its floor plan and entity lists.
+    pub(crate) fn from_parts(
+        floor_plan: FloorPlan,
+        warps: Vec<Warp>,
+        chests: Vec<Chest>,
+        traps: Vec<Trap>,
+        digimon: Vec<DigimonSpawn>,
+        list_placements: ListPlacements,
+    ) -> Self {
+        Self {
+            floor_plan,
+            warps,
+            chests,
+            traps,
+            digimon,
+            list_placements,
+        }
+    }
+
+    /// Where each of the entity lists of the layout is stored in a dungeon
+    /// file.
+    pub fn list_placements(&self) -> ListPlacements {
+        self.list_placements
+    }
+
+    /// Move the chest with the given index to the given coordinates.
+    pub fn move_chest(
+        &mut self,
+        index: usize,
+        x: u8,
+        y: u8,
+    ) -> anyhow::Result<()> {
+        check_position(x, y)?;
+        let entity = entity_mut(&mut self.chests, index, "chest")?;
+        entity.x = x;
+        entity.y = y;
+        Ok(())
+    }
+
+    /// Move the Digimon encounter point with the given index to the given
+    /// coordinates.
+    pub fn move_digimon(
+        &mut self,
+        index: usize,
+        x: u8,
+        y: u8,
+    ) -> anyhow::Result<()> {
+        check_position(x, y)?;
+        let entity =
+            entity_mut(&mut self.digimon, index, "Digimon encounter point")?;
+        entity.x = x;
+        entity.y = y;
+        Ok(())
+    }
+
+    /// Move the trap with the given index to the given coordinates.
+    pub fn move_trap(
+        &mut self,
+        index: usize,
+        x: u8,
+        y: u8,
+    ) -> anyhow::Result<()> {
+        check_position(x, y)?;
+        let entity = entity_mut(&mut self.traps, index, "trap")?;
+        entity.x = x;
+        entity.y = y;
+        Ok(())
+    }
+
+    /// Move the warp with the given index to the given coordinates.
+    pub fn move_warp(
+        &mut self,
+        index: usize,
+        x: u8,
+        y: u8,
+    ) -> anyhow::Result<()> {
+        check_position(x, y)?;
+        let entity = entity_mut(&mut self.warps, index, "warp")?;
+        entity.x = x;
+        entity.y = y;
+        Ok(())
+    }
+
+    /// Parse the layout whose pointer table is at `table_ptr`, recording
+    /// the bytes consumed in `coverage`.  Each entity list is recorded along
+    /// with the record which ends it.
+    pub fn new(
+        raw: &[u8],
+        table_ptr: usize,
+        coverage: &mut Coverage,
+    ) -> anyhow::Result<Self> {
+        if raw.len() < table_ptr + Self::TABLE_SIZE {
+            return Err(anyhow!("truncated layout pointer table"));
+        }
+        coverage
+            .record(table_ptr..table_ptr + Self::TABLE_SIZE, "pointer table");
+        let floor_plan_ptr = parse_ptr(&raw[table_ptr..])
+            .context("parsing floor plan pointer")?;
+        let floor_plan = FloorPlan::new(slice_from(raw, floor_plan_ptr)?)
+            .context("parsing floor plan")?;
+        coverage.record(
+            floor_plan_ptr..floor_plan_ptr + FloorPlan::SIZE,
+            "floor plan",
+        );
+        let warps_ptr = parse_ptr(&raw[table_ptr + 4..])
+            .context("parsing warps pointer")?;
+        let warps = Warp::parse_list(slice_from(raw, warps_ptr)?)
+            .context("parsing warps")?;
+        coverage.record(
+            warps_ptr..warps_ptr + (warps.len() + 1) * Warp::SIZE,
+            "warps",
+        );
+        let chests_ptr = parse_ptr(&raw[table_ptr + 8..])
+            .context("parsing chests pointer")?;
+        let chests = Chest::parse_list(slice_from(raw, chests_ptr)?)
+            .context("parsing chests")?;
+        coverage.record(
+            chests_ptr..chests_ptr + (chests.len() + 1) * Chest::SIZE,
+            "chests",
+        );
+        let traps_ptr = parse_ptr(&raw[table_ptr + 12..])
+            .context("parsing traps pointer")?;
+        let traps = Trap::parse_list(slice_from(raw, traps_ptr)?)
+            .context("parsing traps")?;
+        coverage.record(
+            traps_ptr..traps_ptr + (traps.len() + 1) * Trap::SIZE,
+            "traps",
+        );
+        let digimon_ptr = parse_ptr(&raw[table_ptr + 16..])
+            .context("parsing digimon pointer")?;
+        let digimon = DigimonSpawn::parse_list(slice_from(raw, digimon_ptr)?)
+            .context("parsing digimon")?;
+        coverage.record(
+            digimon_ptr..digimon_ptr + (digimon.len() + 1) * DigimonSpawn::SIZE,
+            "digimon",
+        );
+        let placement = |ptr| {
+            if ptr > table_ptr {
+                ListPlacement::Pooled
+            } else {
+                ListPlacement::Inline
+            }
+        };
+        Ok(Self {
+            floor_plan,
+            warps,
+            chests,
+            traps,
+            digimon,
+            list_placements: ListPlacements {
+                warps: placement(warps_ptr),
+                chests: placement(chests_ptr),
+                traps: placement(traps_ptr),
+                digimon: placement(digimon_ptr),
+            },
+        })
+    }
+
+    /// Find what the player can reach from each spawn warp of the layout.
+    /// See [`pathfinding::analyze_layout`].
+    pub fn reachability(&self) -> Vec<SpawnReachability> {
+        pathfinding::analyze_layout(self)
+    }
+
+    /// Remove the chest with the given index, returning it.  Later
+    /// chests move down one index.
+    pub fn remove_chest(
+        &mut self,
+        index: usize,
+    ) -> anyhow::Result<Chest> {
+        entity_mut(&mut self.chests, index, "chest")?;
+        Ok(self.chests.remove(index))
+    }
+
+    /// Remove the Digimon encounter point with the given index, returning it.
+    /// Later Digimon encounter points move down one index.
+    pub fn remove_digimon(
+        &mut self,
+        index: usize,
+    ) -> anyhow::Result<DigimonSpawn> {
+        entity_mut(&mut self.digimon, index, "Digimon encounter point")?;
+        Ok(self.digimon.remove(index))
+    }
+
+    /// Remove the trap with the given index, returning it.  Later
+    /// traps move down one index.
+    pub fn remove_trap(
+        &mut self,
+        index: usize,
+    ) -> anyhow::Result<Trap> {
+        entity_mut(&mut self.traps, index, "trap")?;
+        Ok(self.traps.remove(index))
+    }
+
+    /// Remove the warp with the given index, returning it.  Later
+    /// warps move down one index.
+    pub fn remove_warp(
+        &mut self,
+        index: usize,
+    ) -> anyhow::Result<Warp> {
+        entity_mut(&mut self.warps, index, "warp")?;
+        Ok(self.warps.remove(index))
+    }
+
+    /// Draw the layout as an ASCII map, followed by a legend explaining the
+    /// characters used.  See [`ascii_map::render_map`].
+    pub fn render_ascii(&self) -> String {
+        let mut map = ascii_map::render_map(self);
+        map.push('\n');
+        map.push_str(ascii_map::LEGEND);
+        map
+    }
+
+    /// Draw the layout as a PNG image, returning the encoded image.  See
+    /// [`png_map::render_png`].
+    pub fn render_png(&self) -> anyhow::Result<Vec<u8>> {
+        png_map::render_png(self)
+    }
+
+    /// The places in the layout where traps may appear.
+    pub fn traps(&self) -> &[Trap] {
+        &self.traps
+    }
+
+    /// Check the layout for problems which would break it in the game.  See
+    /// [`validate::validate_layout`].
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate::validate_layout(self)
+    }
+
+    /// Where the player appears in the layout, and where they can leave it.
+    pub fn warps(&self) -> &[Warp] {
+        &self.warps
+    }
+
+    /// Append the floor plan, inline entity lists and pointer table of the
+    /// layout to the given dungeon file being built, returning the offset of
+    /// the pointer table.  Entity lists placed in the pool are added to
+    /// `pool`, to be written after the last floor table.
+    pub(crate) fn write(
+        &self,
+        raw: &mut Vec<u8>,
+        pool: &mut Vec<PooledList>,
+    ) -> usize {
+        let mut table = [0; 5];
+        table[0] = raw.len();
+        raw.extend(self.floor_plan.to_bytes());
+        let lists = vec![
+            (
+                self.list_placements.warps,
+                encode_list(self.warps.iter().map(Warp::to_bytes), Warp::SIZE),
+            ),
+            (
+                self.list_placements.chests,
+                encode_list(
+                    self.chests.iter().map(Chest::to_bytes),
+                    Chest::SIZE,
+                ),
+            ),
+            (
+                self.list_placements.traps,
+                encode_list(self.traps.iter().map(Trap::to_bytes), Trap::SIZE),
+            ),
+            (
+                self.list_placements.digimon,
+                encode_list(
+                    self.digimon.iter().map(DigimonSpawn::to_bytes),
+                    DigimonSpawn::SIZE,
+                ),
+            ),
+        ];
+        let mut pooled = Vec::new();
+        for (i, (placement, list)) in lists.into_iter().enumerate() {
+            match placement {
+                ListPlacement::Inline => {
+                    table[i + 1] = raw.len();
+                    raw.extend(list);
+                    pad_to_alignment(raw);
+                },
+                ListPlacement::Pooled => pooled.push((i + 1, list)),
+            }
+        }
+        let table_ptr = raw.len();
+        for ptr in &table {
+            raw.extend_from_slice(&encode_ptr(*ptr));
+        }
+        pool.extend(pooled.into_iter().map(|(i, list)| PooledList {
+            ptr_offset: table_ptr + i * 4,
+            list,
+        }));
+        table_ptr
+    }
+}
@@ -0,0 +1,93 @@
+//! This crate provides a parser for the dungeon (level) files of the game
+//! "Digimon World 2".
+//!
+//! The main entry point is [`Dungeon`], which can be constructed from the
+//! raw contents of a `DUNGxxxx.BIN` file:
+//!
+//! ```no_run
+//! use digimon::Dungeon;
+//! use std::convert::TryFrom;
+//!
+//! let bytes = std::fs::read("DUNG4000.BIN").unwrap();
+//! let dungeon = Dungeon::try_from(&bytes).unwrap();
+//! ```
+
+pub mod analysis;
+pub mod ascii_map;
+pub mod character_table;
+pub mod chest;
+pub mod coverage;
+pub mod digimon_spawn;
+pub mod disc;
+pub mod dungeon;
+pub mod floor;
+pub mod floor_plan;
+pub mod game_text;
+pub mod layout;
+pub mod patch;
+pub mod pathfinding;
+pub mod png_map;
+pub mod pointers;
+pub mod project;
+pub mod relocation;
+pub mod sector;
+pub mod text;
+pub mod trap;
+pub mod validate;
+pub mod warp;
+
+pub use character_table::CharacterTable;
+pub use chest::Chest;
+pub use coverage::Coverage;
+pub use digimon_spawn::{
+    DigimonSpawn,
+    Encounter,
+};
+pub use disc::{
+    Disc,
+    DiscFile,
+};
+pub use dungeon::Dungeon;
+pub use floor::Floor;
+pub use floor_plan::{
+    FloorPlan,
+    Tile,
+};
+pub use game_text::{
+    ControlCode,
+    GameText,
+    Segment,
+};
+pub use layout::{
+    Layout,
+    LayoutId,
+    ListPlacement,
+    ListPlacements,
+};
+pub use pointers::{
+    parse_list,
+    parse_ptr,
+    parse_records,
+};
+pub use relocation::{
+    DungeonImage,
+    FreeSpace,
+};
+pub use text::{
+    encode_string,
+    parse_string,
+};
+pub use trap::{
+    Trap,
+    TrapColour,
+    TrapFamily,
+    TrapKind,
+};
+pub use validate::{
+    Diagnostic,
+    Severity,
+};
+pub use warp::{
+    Warp,
+    WarpKind,
+};
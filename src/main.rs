@@ -2,19 +2,33 @@ use anyhow::{
     anyhow,
     Context as _,
 };
-use once_cell::sync::Lazy;
+use digimon::{
+    analysis::{
+        self,
+        WordTarget,
+    },
+    ascii_map,
+    coverage,
+    patch,
+    pathfinding::EntityKind,
+    png_map,
+    project,
+    CharacterTable,
+    Diagnostic,
+    Disc,
+    Dungeon,
+    DungeonImage,
+    GameText,
+    Segment,
+    Severity,
+    WarpKind,
+};
 use std::{
     collections::{
-        HashMap,
+        BTreeMap,
         HashSet,
     },
-    convert::{
-        TryFrom,
-        TryInto,
-    },
-    fmt::Display,
-    fs::File,
-    io::Read as _,
+    convert::TryFrom,
     path::{
         Path,
         PathBuf,
@@ -22,386 +36,715 @@ use std::{
 };
 use structopt::StructOpt;
 
-fn parse_ptr(raw: &[u8]) -> anyhow::Result<usize> {
-    if raw.len() < 4 {
-        return Err(anyhow!("truncated pointer"));
-    }
-    let (ptr_raw, _) = raw.split_at(std::mem::size_of::<u32>());
-    let ptr = u32::from_le_bytes(ptr_raw.try_into().unwrap());
-    Ok(ptr as usize)
+#[derive(Clone, StructOpt)]
+struct DungeonOpts {
+    /// Path to dungeon file to parse, or its name if reading it from a disc
+    /// image
+    #[structopt(default_value = "../../data/DUNG4000.BIN")]
+    dungeon_file_relative_path: PathBuf,
+
+    /// Disc image (ISO, raw BIN, or CUE sheet) from which to read the
+    /// dungeon file
+    #[structopt(long)]
+    disc: Option<PathBuf>,
 }
 
-fn parse_list(raw: &[u8]) -> anyhow::Result<&[u8]> {
-    let mut i = 0;
-    loop {
-        if i >= raw.len() {
-            return Err(anyhow!("truncated list"));
+impl DungeonOpts {
+    fn load(&self) -> anyhow::Result<Dungeon> {
+        if self.disc.is_some() {
+            return Dungeon::try_from(&self.load_raw()?)
+                .context("parsing dungeon file");
         }
-        if raw[i] == 0xFF {
-            break;
+        let dungeon_file_path =
+            program_relative_path(&self.dungeon_file_relative_path)?;
+        Dungeon::try_from(&dungeon_file_path).context("parsing dungeon file")
+    }
+
+    fn load_raw(&self) -> anyhow::Result<Vec<u8>> {
+        if let Some(disc) = &self.disc {
+            let name = self
+                .dungeon_file_relative_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow!("no dungeon file name given"))?;
+            let mut disc = Disc::open(disc)?;
+            let file = disc.dungeon_file(name)?;
+            return disc.read_file(&file);
         }
-        i += 1;
+        let dungeon_file_path =
+            program_relative_path(&self.dungeon_file_relative_path)?;
+        std::fs::read(&dungeon_file_path).context(format!(
+            "reading dungeon file \"{}\"",
+            dungeon_file_path.display()
+        ))
     }
-    Ok(&raw[..i])
 }
 
-static CHARACTER_MAP: Lazy<HashMap<u16, &'static str>> = Lazy::new(|| {
-    maplit::hashmap! {
-        0x0 => "0",
-        0x1 => "1",
-        0x2 => "2",
-        0x3 => "3",
-        0x4 => "4",
-        0x5 => "5",
-        0x6 => "6",
-        0x7 => "7",
-        0x8 => "8",
-        0x9 => "9",
-        0x0A => "A",
-        0x0B => "B",
-        0x0C => "C",
-        0x0D => "D",
-        0x0E => "E",
-        0x0F => "F",
-        0x10 => "G",
-        0x11 => "H",
-        0x12 => "I",
-        0x13 => "J",
-        0x14 => "K",
-        0x15 => "L",
-        0x16 => "M",
-        0x17 => "N",
-        0x18 => "O",
-        0x19 => "P",
-        0x1A => "Q",
-        0x1B => "R",
-        0x1C => "S",
-        0x1D => "T",
-        0x1E => "U",
-        0x1F => "V",
-        0x20 => "W",
-        0x21 => "X",
-        0x22 => "Y",
-        0x23 => "Z",
-        0x24 => "a",
-        0x25 => "b",
-        0x26 => "c",
-        0x27 => "d",
-        0x28 => "e",
-        0x29 => "f",
-        0x2A => "g",
-        0x2B => "h",
-        0x2C => "i",
-        0x2D => "j",
-        0x2E => "k",
-        0x2F => "l",
-        0x30 => "m",
-        0x31 => "n",
-        0x32 => "o",
-        0x33 => "p",
-        0x34 => "q",
-        0x35 => "r",
-        0x36 => "s",
-        0x37 => "t",
-        0x38 => "u",
-        0x39 => "v",
-        0x3A => "w",
-        0x3B => "x",
-        0x3C => "y",
-        0x3D => "z",
-        0x41 => "<SQUARE>",
-        0x44 => "?",
-        0x45 => "!",
-        0x46 => "/",
-        0x49 => "-",
-        0x54 => ",",
-        0x55 => ".",
-        0x56 => "",
-        0x5B => "PLUS SIGN",
-        0xFB => "<X>",
-        0xFC => "<NEW BOX>",
-        0xFD => " ",
-        0xFE => "<ENTER>",
-        0xF000 => "Akira",
-        0xF006 => "Digimon",
-        0xF007 => "you",
-        0xF008 => "the",
-        0xF009 => "Digi-Beetle",
-        0xF00A => "Domain",
-        0xF00B => "Guard",
-        0xF00C => "Tamer",
-        0xF00D => "here",
-        0xF00E => "have",
-        0xF00F => "Knights",
-        0xF010 => "and",
-        0xF011 => "thing",
-        0xF012 => "Security",
-        0xF013 => "that",
-        0xF014 => "Bertran",
-        0xF015 => "Tournament",
-        0xF016 => "Crimson",
-        0xF018 => "something",
-        0xF019 => "Item",
-        0xF01A => "Falcon",
-        0xF01B => "for",
-        0xF01C => "That's",
-        0xF01D => "Commander",
-        0xF01E => "Blood",
-        0xF01F => "Leader",
-        0xF020 => "Attendant",
-        0xF021 => "Cecilia",
-        0xF022 => "all",
-        0xF023 => "mission",
-        0xF024 => "this",
-        0xF026 => "Archive",
-        0xF027 => "Black",
-        0xF028 => "I'll",
-        0xF029 => "are",
-        0xF02A => "Sword",
-        0xF02B => "right",
-        0xF02C => "Digivolve",
-        0xF02D => "enter",
-        0xF02E => "What",
-        0xF02F => "will",
-        0xF030 => "come",
-        0xF031 => "You",
-        0xF032 => "Coliseum",
-        0xF033 => "about",
-        0xF034 => "don't",
-        0xF035 => "anything",
-        0xF037 => "Parts",
-        0xF038 => "where",
-        0xF039 => "The",
-        0xF03A => "know",
-        0xF03B => "Leomon",
-        0xF03C => "want",
-        0xF03D => "Oldman",
-        0xF03E => "like",
-        0xF03F => "need",
-        0xF040 => "Chief",
-        0xF041 => "with",
-        0xF042 => "Thank",
-        0xF044 => "Island",
-        0xF045 => "can",
-        0xF046 => "really",
-        0xF047 => "Blue",
-        0xF048 => "time",
-    }
-});
+#[derive(Clone, StructOpt)]
+enum Command {
+    /// Apply an IPS or BPS patch made by `make-patch` to a dungeon file or
+    /// disc image, verifying the checksums of BPS patches
+    ApplyPatch {
+        /// Path to the file to patch
+        original: PathBuf,
 
-fn parse_string_piece(
-    mut raw: &[u8]
-) -> anyhow::Result<Option<(&'static str, &[u8])>> {
-    if raw.is_empty() {
-        return Err(anyhow!("truncated character"));
-    }
-    let first = raw[0];
-    raw = &raw[1..];
-    let encoding = match first {
-        0xFF => return Ok(None),
-        0xF0 => {
-            if raw.is_empty() {
-                return Err(anyhow!("truncated character"));
-            }
-            let second = raw[0];
-            raw = &raw[1..];
-            (first as u16) << 8 | (second as u16)
-        },
-        first => first as u16,
-    };
-    Ok(Some((
-        CHARACTER_MAP
-            .get(&encoding)
-            .copied()
-            .ok_or_else(|| anyhow!("illegal character 0x{:02X}", encoding))?,
-        raw,
-    )))
+        /// Path to the patch to apply
+        patch: PathBuf,
+
+        /// File in which to write the patched file
+        #[structopt(long, short)]
+        output: PathBuf,
+    },
+
+    /// Build a dungeon file from a project directory written by
+    /// `export-project`
+    CompileProject {
+        /// Path to project directory to read
+        input: PathBuf,
+
+        /// Dungeon file to update in place, keeping structures which have
+        /// not changed where they are, rather than writing a new file
+        #[structopt(long)]
+        base: Option<PathBuf>,
+
+        /// Dungeon file to write
+        #[structopt(long, short)]
+        output: PathBuf,
+    },
+
+    /// List the bytes of a dungeon file not consumed by the parser, as hex
+    /// dumps, with the percentage of the file which was consumed
+    Coverage {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+
+        /// Also list gaps which only pad structures to four-byte alignment
+        #[structopt(long)]
+        all: bool,
+    },
+
+    /// List the dungeon files of a disc image (ISO, raw BIN, or CUE sheet)
+    DiscFiles {
+        /// Path to disc image to read
+        image: PathBuf,
+    },
+
+    /// Print the floors of a dungeon, with the floor plans of their layouts
+    /// and their Digimon encounters
+    Dump {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+    },
+
+    /// Report the second word of each floor table of the given dungeon
+    /// files, whose meaning is not known, and what it would point to if it
+    /// were a pointer
+    FloorWords {
+        /// Paths to dungeon files to analyze
+        #[structopt(required = true)]
+        dungeon_file_relative_paths: Vec<PathBuf>,
+    },
+
+    /// Write a dungeon as a JSON document
+    ExportJson {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+
+        /// File in which to write the document, rather than printing it
+        #[structopt(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write a dungeon as a project directory of TOML files, with floor plans
+    /// drawn as ASCII art, which can be edited and compiled back
+    ExportProject {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+
+        /// Directory in which to write the project
+        #[structopt(long, short, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Build a dungeon file from a JSON document written by `export-json`
+    ImportJson {
+        /// Path to JSON document to read
+        input: PathBuf,
+
+        /// Dungeon file to update in place, keeping structures which have
+        /// not changed where they are, rather than writing a new file
+        #[structopt(long)]
+        base: Option<PathBuf>,
+
+        /// Dungeon file to write
+        #[structopt(long, short)]
+        output: PathBuf,
+    },
+
+    /// Check the layouts of a dungeon for problems which would break them in
+    /// the game, failing if any errors are found
+    Lint {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+    },
+
+    /// Compare an original and a modified dungeon file or disc image, and
+    /// write a patch which turns one into the other
+    MakePatch {
+        /// Path to the original file
+        original: PathBuf,
+
+        /// Path to the modified file
+        modified: PathBuf,
+
+        /// File in which to write an IPS patch
+        #[structopt(long)]
+        ips: Option<PathBuf>,
+
+        /// File in which to write a BPS patch
+        #[structopt(long)]
+        bps: Option<PathBuf>,
+    },
+
+    /// Draw the layouts of a dungeon as ASCII maps
+    Map {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+
+        /// Only draw the layouts of this floor (counting from 1)
+        #[structopt(long)]
+        floor: Option<usize>,
+    },
+
+    /// Report, for each spawn warp of each layout of a dungeon, what the
+    /// player cannot reach and how far away the next floor and exit warps
+    /// are
+    Reach {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+    },
+
+    /// Draw each layout of each floor of a dungeon as a PNG image
+    Png {
+        #[structopt(flatten)]
+        dungeon: DungeonOpts,
+
+        /// Directory in which to write the images
+        #[structopt(long, short, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Replace a dungeon file inside a disc image (ISO, raw BIN, or CUE
+    /// sheet), in place
+    WriteDisc {
+        /// Path to disc image to modify
+        image: PathBuf,
+
+        /// Dungeon file to write into the disc image
+        dungeon: PathBuf,
+
+        /// Name of the dungeon file on the disc to replace, if not the same
+        /// as the name of the dungeon file written
+        #[structopt(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Clone, StructOpt)]
+struct Opts {
+    /// Character table file (`hex=text` lines) to merge over the built-in
+    /// table when decoding and encoding text
+    #[structopt(long, global = true)]
+    table: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    command: Command,
 }
 
-fn parse_string(mut raw: &[u8]) -> anyhow::Result<String> {
-    let mut value = String::new();
-    while let Some((piece, rest)) = parse_string_piece(raw)? {
-        value.push_str(piece);
-        raw = rest;
+impl Opts {
+    fn character_table(&self) -> anyhow::Result<CharacterTable> {
+        let mut table = CharacterTable::builtin().clone();
+        if let Some(path) = &self.table {
+            table.merge(&CharacterTable::load_tbl(path)?);
+        }
+        Ok(table)
     }
-    Ok(value)
 }
 
-struct FloorPlan {
-    // 48 rows, 32 columns
-    tiles: [[u8; 32]; 48],
+fn program_relative_path(path: &Path) -> anyhow::Result<PathBuf> {
+    Ok(std::env::current_exe()
+        .context("getting program directory path")?
+        .parent()
+        .expect("unable to get directory containing program")
+        .join(path))
 }
 
-impl FloorPlan {
-    fn new(mut raw: &[u8]) -> anyhow::Result<Self> {
-        let mut tiles = [[0_u8; 32]; 48];
-        tiles.iter_mut().try_for_each::<_, anyhow::Result<()>>(|row| {
-            row.iter_mut().try_for_each::<_, anyhow::Result<()>>(|tile| {
-                *tile = raw
-                    .first()
-                    .copied()
-                    .ok_or_else(|| anyhow!("truncated floor plan"))?;
-                raw = &raw[1..];
-                Ok(())
-            })
-        })?;
-        Ok(Self {
-            tiles,
-        })
-    }
+fn apply_patch(
+    original: &Path,
+    patch: &Path,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let original_raw = std::fs::read(original)
+        .context(format!("reading \"{}\"", original.display()))?;
+    let patch_raw = std::fs::read(patch)
+        .context(format!("reading \"{}\"", patch.display()))?;
+    let modified = patch::apply_patch(&original_raw, &patch_raw)
+        .context(format!("applying patch \"{}\"", patch.display()))?;
+    std::fs::write(output, modified)
+        .context(format!("writing \"{}\"", output.display()))
 }
 
-impl Display for FloorPlan {
-    fn fmt(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-    ) -> std::fmt::Result {
-        for y in 0..48 {
-            for x in 0..32 {
-                if x != 0 {
-                    write!(f, " ")?;
-                }
-                write!(f, "{:02X}", self.tiles[y][x])?;
+fn coverage(
+    raw: &[u8],
+    all: bool,
+) -> anyhow::Result<()> {
+    let (_, coverage) =
+        Dungeon::parse_with_coverage(raw).context("parsing dungeon file")?;
+    let gaps = coverage.gaps();
+    let mut padding = 0;
+    for gap in &gaps {
+        let bytes = &raw[gap.clone()];
+        let is_padding = gap.len() < 4
+            && gap.end % 4 == 0
+            && bytes.iter().all(|&byte| byte == 0);
+        if is_padding {
+            padding += gap.len();
+            if !all {
+                continue;
             }
-            writeln!(f)?;
         }
-        Ok(())
+        println!("Gap at 0x{:08X} ({} bytes):", gap.start, gap.len());
+        print!("{}", coverage::hex_dump(bytes, gap.start));
+    }
+    println!(
+        "Covered {} of {} bytes ({:.2}%), leaving {} gaps",
+        coverage.covered_len(),
+        coverage.file_len(),
+        coverage.percentage(),
+        gaps.len()
+    );
+    if !all {
+        println!("({} bytes of alignment padding not listed)", padding);
     }
+    Ok(())
 }
 
-struct Layout {}
-
-impl Layout {
-    fn new(
-        raw: &[u8],
-        table_ptr: usize,
-    ) -> anyhow::Result<Self> {
-        if raw.len() < table_ptr + 20 {
-            return Err(anyhow!("truncated layout pointer table"));
-        }
-        let floor_plan_ptr = parse_ptr(&raw[table_ptr..])
-            .context("parsing floor plan pointer")?;
-        let floor_plan = FloorPlan::new(&raw[floor_plan_ptr..])?;
-        println!("Floor plan is at {:X}", floor_plan_ptr);
-        println!("Floor plan:");
-        println!("{}", floor_plan);
-        let warps_ptr = parse_ptr(&raw[table_ptr + 4..])
-            .context("parsing warps pointer")?;
-        let chests_ptr = parse_ptr(&raw[table_ptr + 8..])
-            .context("parsing chests pointer")?;
-        let traps_ptr = parse_ptr(&raw[table_ptr + 12..])
-            .context("parsing traps pointer")?;
-        let digimon_ptr = parse_ptr(&raw[table_ptr + 16..])
-            .context("parsing digimon pointer")?;
-        Ok(Self {})
+fn disc_files(image: &Path) -> anyhow::Result<()> {
+    let mut disc = Disc::open(image)?;
+    for file in disc.dungeon_files()? {
+        println!("{} ({} bytes at sector {})", file.path, file.len, file.lba);
     }
+    Ok(())
 }
 
-struct Floor {}
-
-impl Floor {
-    fn new(
-        raw: &[u8],
-        table_ptr: usize,
-    ) -> anyhow::Result<Self> {
-        let name_ptr =
-            parse_ptr(&raw[table_ptr..]).context("parsing name pointer")?;
-        let title = parse_string(&raw[name_ptr..]).context("parsing name")?;
-        println!("Floor: \"{}\"", title);
-        if raw.len() < table_ptr + 8 {
-            return Err(anyhow!(
-                "floor table truncated before layout pointers"
-            ));
-        }
-        let mut next_layout_ptr_offset = &raw[table_ptr + 8..];
-        let mut layout_ptrs = HashSet::new();
-        for i in 0..8 {
-            let layout_ptr = parse_ptr(next_layout_ptr_offset)
-                .context("parsing layout pointer")?;
-            next_layout_ptr_offset = &next_layout_ptr_offset[4..];
-            if layout_ptrs.insert(layout_ptr) {
-                let layout = Layout::new(raw, layout_ptr)
-                    .context(format!("parsing layout {}", i + 1))?;
+fn dump(
+    dungeon: &Dungeon,
+    table: &CharacterTable,
+) {
+    let mut printed_layouts = HashSet::new();
+    for floor in dungeon.floors() {
+        println!("Floor: \"{}\"", floor.name().display_with(table));
+        let chances = floor
+            .layout_probabilities()
+            .into_iter()
+            .map(|(id, probability)| {
+                format!("layout {}: {:.1}%", id.0 + 1, probability * 100.0)
+            })
+            .collect::<Vec<_>>();
+        println!("Layout chances: {}", chances.join(", "));
+        for &id in floor.layout_slots() {
+            if printed_layouts.insert(id) {
+                let layout = &dungeon.layouts()[id.0];
+                println!("Layout {}:", id.0 + 1);
+                println!("{}", layout.floor_plan());
+                for spawn in layout.digimon() {
+                    let groups = spawn
+                        .group_probabilities()
+                        .into_iter()
+                        .map(|(group, probability)| {
+                            format!(
+                                "group {}: {:.0}%",
+                                group,
+                                probability * 100.0
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    println!(
+                        "Digimon at ({}, {}): {}",
+                        spawn.x,
+                        spawn.y,
+                        groups.join(", ")
+                    );
+                }
             }
         }
-        Ok(Self {})
     }
 }
 
-struct Dungeon {}
-
-impl Dungeon {}
+fn lint(
+    dungeon: &Dungeon,
+    table: &CharacterTable,
+) -> anyhow::Result<()> {
+    let mut diagnostics = dungeon.validate();
+    for (i, floor) in dungeon.floors().iter().enumerate() {
+        let name = GameText::parse_with(&floor.name().to_bytes(), table)?;
+        if name
+            .segments()
+            .iter()
+            .any(|segment| matches!(segment, Segment::Unknown(_)))
+        {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                layout: None,
+                position: None,
+                message: format!(
+                    "name of floor {} (\"{}\") has codes missing from the \
+                     character table",
+                    i + 1,
+                    name.display_with(table)
+                ),
+            });
+        }
+    }
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    println!("{} error(s), {} warning(s)", errors, diagnostics.len() - errors);
+    if errors > 0 {
+        return Err(anyhow!("dungeon has errors"));
+    }
+    Ok(())
+}
 
-impl TryFrom<&[u8]> for Dungeon {
-    type Error = anyhow::Error;
+fn make_patch(
+    original: &Path,
+    modified: &Path,
+    ips: Option<&Path>,
+    bps: Option<&Path>,
+) -> anyhow::Result<()> {
+    if ips.is_none() && bps.is_none() {
+        return Err(anyhow!("no patch to write (use --ips and/or --bps)"));
+    }
+    let original = std::fs::read(original)
+        .context(format!("reading \"{}\"", original.display()))?;
+    let modified = std::fs::read(modified)
+        .context(format!("reading \"{}\"", modified.display()))?;
+    if let Some(path) = bps {
+        std::fs::write(path, patch::make_bps(&original, &modified))
+            .context(format!("writing \"{}\"", path.display()))?;
+    }
+    if let Some(path) = ips {
+        // Files too large for IPS, such as disc images, can still be
+        // patched with the BPS patch if one was asked for.
+        match patch::make_ips(&original, &modified) {
+            Ok(ips) => std::fs::write(path, ips)
+                .context(format!("writing \"{}\"", path.display()))?,
+            Err(error) if bps.is_some() => eprintln!(
+                "warning: not writing \"{}\": {}",
+                path.display(),
+                error
+            ),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(())
+}
 
-    fn try_from(raw: &[u8]) -> Result<Self, Self::Error> {
-        println!("Dungeon raw file is {} bytes", raw.len());
-        let mut floors = Vec::new();
-        let mut raw_ptrs = raw;
-        let mut i = 1;
-        loop {
-            let floor_ptr =
-                parse_ptr(raw_ptrs).context("parsing next floor pointer")?;
-            raw_ptrs = &raw_ptrs[4..];
-            if floor_ptr == 0 {
-                break;
+fn map(
+    dungeon: &Dungeon,
+    floor: Option<usize>,
+    table: &CharacterTable,
+) -> anyhow::Result<()> {
+    let floors = dungeon
+        .floors()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| floor.is_none_or(|floor| floor == i + 1));
+    let mut printed_any = false;
+    let mut printed_layouts = HashSet::new();
+    for (i, floor) in floors {
+        println!("Floor {}: \"{}\"", i + 1, floor.name().display_with(table));
+        for &id in floor.layout_slots() {
+            if printed_layouts.insert(id) {
+                println!("Layout {}:", id.0 + 1);
+                println!("{}", ascii_map::render_map(&dungeon.layouts()[id.0]));
             }
-            floors.push(
-                Floor::new(raw, floor_ptr)
-                    .context(format!("parsing floor {}", i))?,
-            );
-            i += 1;
         }
-        Ok(Self {})
+        printed_any = true;
+    }
+    if !printed_any {
+        return Err(anyhow!("no such floor"));
     }
+    print!("{}", ascii_map::LEGEND);
+    Ok(())
 }
 
-impl TryFrom<&Vec<u8>> for Dungeon {
-    type Error = anyhow::Error;
-
-    fn try_from(raw: &Vec<u8>) -> Result<Self, Self::Error> {
-        let raw: &[u8] = &raw;
-        Self::try_from(raw)
+fn export_json(
+    dungeon: &Dungeon,
+    output: Option<&Path>,
+    table: &CharacterTable,
+) -> anyhow::Result<()> {
+    let json = GameText::with_serde_table(table, || {
+        serde_json::to_string_pretty(dungeon)
+    })
+    .context("serializing dungeon")?;
+    if let Some(output) = output {
+        std::fs::write(output, json)
+            .context(format!("writing \"{}\"", output.display()))?;
+    } else {
+        println!("{}", json);
     }
+    Ok(())
 }
 
-impl TryFrom<&Path> for Dungeon {
-    type Error = anyhow::Error;
-
-    fn try_from(path: &Path) -> Result<Self, Self::Error> {
-        let mut file = File::open(path)
-            .context(format!("opening dungeon file \"{:?}\"", path))?;
-        let mut dungeon_raw = Vec::new();
-        file.read_to_end(&mut dungeon_raw).context("reading dungeon file")?;
-        Self::try_from(&dungeon_raw)
+fn floor_words(
+    paths: &[PathBuf],
+    table: &CharacterTable,
+) -> anyhow::Result<()> {
+    let mut values = BTreeMap::new();
+    for path in paths {
+        let path = program_relative_path(path)?;
+        let raw = std::fs::read(&path)
+            .context(format!("reading \"{}\"", path.display()))?;
+        let reports = analysis::floor_word_report(&raw)
+            .context(format!("analyzing \"{}\"", path.display()))?;
+        println!("{}:", path.display());
+        for report in reports {
+            let target = match &report.target {
+                WordTarget::Null => String::from("null"),
+                WordTarget::OutOfRange => String::from("beyond end of file"),
+                WordTarget::Unclaimed => String::from("unclaimed bytes"),
+                WordTarget::Region {
+                    description,
+                    offset,
+                } => format!("{} bytes into {}", offset, description),
+            };
+            println!(
+                "  floor {:2} {:24} 0x{:08X}  {}{}",
+                report.floor,
+                format!("\"{}\"", report.name.display_with(table)),
+                report.value,
+                target,
+                if report.target.is_likely_pointer(report.value) {
+                    " (likely pointer)"
+                } else {
+                    ""
+                }
+            );
+            *values.entry(report.value).or_insert(0) += 1;
+        }
+    }
+    println!("Values seen:");
+    for (value, count) in values {
+        println!("  0x{:08X}: {} floor(s)", value, count);
     }
+    Ok(())
 }
 
-impl TryFrom<&PathBuf> for Dungeon {
-    type Error = anyhow::Error;
+fn import_json(
+    input: &Path,
+    base: Option<&Path>,
+    output: &Path,
+    table: &CharacterTable,
+) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(input)
+        .context(format!("reading \"{}\"", input.display()))?;
+    let dungeon: Dungeon =
+        GameText::with_serde_table(table, || serde_json::from_str(&json))
+            .context("parsing JSON document")?;
+    write_dungeon(&dungeon, base, output)
+}
 
-    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
-        let path: &Path = &path;
-        Self::try_from(path)
+fn reach(dungeon: &Dungeon) {
+    for (i, layout) in dungeon.layouts().iter().enumerate() {
+        println!("Layout {}:", i + 1);
+        for spawn in layout.reachability() {
+            let exits = spawn
+                .exits
+                .iter()
+                .map(|(warp, distance)| {
+                    let kind = if warp.kind == WarpKind::Exit {
+                        "exit"
+                    } else {
+                        "next floor"
+                    };
+                    let distance = distance.map_or_else(
+                        || String::from("unreachable"),
+                        |distance| format!("{} steps", distance),
+                    );
+                    format!(
+                        "{} at ({}, {}): {}",
+                        kind, warp.x, warp.y, distance
+                    )
+                })
+                .collect::<Vec<_>>();
+            println!(
+                "  Spawn at ({}, {}): {}",
+                spawn.spawn.x,
+                spawn.spawn.y,
+                if exits.is_empty() {
+                    String::from("no next floor or exit warps")
+                } else {
+                    exits.join(", ")
+                }
+            );
+            for entity in &spawn.unreachable {
+                let kind = match entity.kind {
+                    EntityKind::Warp => "warp",
+                    EntityKind::Chest => "chest",
+                    EntityKind::Digimon => "Digimon",
+                };
+                println!(
+                    "    Unreachable: {} {} at ({}, {})",
+                    kind,
+                    entity.index + 1,
+                    entity.x,
+                    entity.y
+                );
+            }
+        }
     }
 }
 
-#[derive(Clone, StructOpt)]
-struct Opts {
-    /// Path to dungeon file to parse
-    #[structopt(default_value = "../../data/DUNG4000.BIN")]
-    dungeon_file_relative_path: PathBuf,
+fn write_disc(
+    image: &Path,
+    dungeon: &Path,
+    name: Option<&str>,
+) -> anyhow::Result<()> {
+    let raw = std::fs::read(dungeon)
+        .context(format!("reading \"{}\"", dungeon.display()))?;
+    Dungeon::try_from(&raw)
+        .context(format!("parsing dungeon file \"{}\"", dungeon.display()))?;
+    let name = match name {
+        Some(name) => name,
+        None => dungeon
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("no dungeon file name given"))?,
+    };
+    let mut disc = Disc::open_writable(image)?;
+    let file = disc.dungeon_file(name)?;
+    let file = disc.replace_file(&file, &raw)?;
+    println!(
+        "Wrote {} ({} bytes, {} sectors allocated)",
+        file.path,
+        file.len,
+        file.sectors()
+    );
+    Ok(())
+}
+
+fn write_dungeon(
+    dungeon: &Dungeon,
+    base: Option<&Path>,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let raw = if let Some(base) = base {
+        let raw = std::fs::read(base)
+            .context(format!("reading \"{}\"", base.display()))?;
+        let mut image = DungeonImage::new(raw)
+            .context(format!("parsing dungeon file \"{}\"", base.display()))?;
+        image.update(dungeon)?;
+        image.into_bytes()
+    } else {
+        dungeon.to_bytes()
+    };
+    std::fs::write(output, raw)
+        .context(format!("writing \"{}\"", output.display()))
 }
 
 fn main() -> anyhow::Result<()> {
     let opts: Opts = Opts::from_args();
-    let dungeon_file_path = std::env::current_exe()
-        .context("getting program directory path")?
-        .parent()
-        .expect("unable to get directory containing program")
-        .join(opts.dungeon_file_relative_path);
-    let dungeon = Dungeon::try_from(&dungeon_file_path)
-        .context("parsing dungeon file")?;
+    let table = opts.character_table()?;
+    match opts.command {
+        Command::ApplyPatch {
+            original,
+            patch,
+            output,
+        } => apply_patch(&original, &patch, &output)?,
+        Command::Coverage {
+            dungeon,
+            all,
+        } => coverage(&dungeon.load_raw()?, all)?,
+        Command::CompileProject {
+            input,
+            base,
+            output,
+        } => write_dungeon(
+            &project::read_project_with(&input, &table)?,
+            base.as_deref(),
+            &output,
+        )?,
+        Command::DiscFiles {
+            image,
+        } => disc_files(&image)?,
+        Command::Dump {
+            dungeon,
+        } => dump(&dungeon.load()?, &table),
+        Command::ExportJson {
+            dungeon,
+            output,
+        } => export_json(&dungeon.load()?, output.as_deref(), &table)?,
+        Command::ExportProject {
+            dungeon,
+            output,
+        } => {
+            std::fs::create_dir_all(&output).context(format!(
+                "creating output directory \"{}\"",
+                output.display()
+            ))?;
+            for path in
+                project::write_project_with(&dungeon.load()?, &output, &table)?
+            {
+                println!("Wrote {}", path.display());
+            }
+        },
+        Command::FloorWords {
+            dungeon_file_relative_paths,
+        } => floor_words(&dungeon_file_relative_paths, &table)?,
+        Command::ImportJson {
+            input,
+            base,
+            output,
+        } => import_json(&input, base.as_deref(), &output, &table)?,
+        Command::Lint {
+            dungeon,
+        } => lint(&dungeon.load()?, &table)?,
+        Command::MakePatch {
+            original,
+            modified,
+            ips,
+            bps,
+        } => make_patch(&original, &modified, ips.as_deref(), bps.as_deref())?,
+        Command::Map {
+            dungeon,
+            floor,
+        } => map(&dungeon.load()?, floor, &table)?,
+        Command::Reach {
+            dungeon,
+        } => reach(&dungeon.load()?),
+        Command::Png {
+            dungeon,
+            output,
+        } => {
+            std::fs::create_dir_all(&output).context(format!(
+                "creating output directory \"{}\"",
+                output.display()
+            ))?;
+            for path in png_map::write_dungeon_pngs(&dungeon.load()?, &output)?
+            {
+                println!("Wrote {}", path.display());
+            }
+        },
+        Command::WriteDisc {
+            image,
+            dungeon,
+            name,
+        } => write_disc(&image, &dungeon, name.as_deref())?,
+    }
     Ok(())
 }
@@ -0,0 +1,379 @@
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+
+/// The bytes which begin every IPS patch.
+const IPS_HEADER: &[u8] = b"PATCH";
+
+/// The bytes which end the records of every IPS patch.
+const IPS_FOOTER: &[u8] = b"EOF";
+
+/// The offset which cannot begin an IPS record, since it would be read as
+/// the footer of the patch.
+const IPS_FOOTER_OFFSET: usize = 0x45_4F46;
+
+/// The largest file an IPS patch can describe, since offsets are three
+/// bytes.
+const IPS_MAX_LEN: usize = 0x100_0000;
+
+/// The largest number of bytes of one IPS record.
+const IPS_MAX_RECORD: usize = 0xFFFF;
+
+/// The bytes which begin every BPS patch.
+const BPS_HEADER: &[u8] = b"BPS1";
+
+/// The kinds of patch which can be made and applied.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PatchFormat {
+    /// International Patching System: a list of byte ranges to overwrite,
+    /// limited to files of 16 MiB, and with no way to check that a patch is
+    /// applied to the right file.
+    Ips,
+
+    /// Binary Patching System (beat): a list of copy actions, with CRC32
+    /// checksums of the original file, the modified file and the patch.
+    Bps,
+}
+
+impl PatchFormat {
+    /// Identify the format of the given patch from its header.
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(IPS_HEADER) {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(BPS_HEADER) {
+            Some(PatchFormat::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+static CRC32_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut table = [0; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1)
+                ^ if crc & 1 == 0 {
+                    0
+                } else {
+                    0xEDB8_8320
+                };
+        }
+        *entry = crc;
+    }
+    table
+});
+
+/// Compute the CRC32 checksum (as used by zip and PNG) of the given bytes.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        (crc >> 8) ^ CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize]
+    })
+}
+
+/// Apply the given patch, in either format, to the given original file,
+/// returning the modified file.  See [`apply_ips`] and [`apply_bps`].
+pub fn apply_patch(
+    original: &[u8],
+    patch: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(original, patch),
+        Some(PatchFormat::Bps) => apply_bps(original, patch),
+        None => Err(anyhow!("not an IPS or BPS patch")),
+    }
+}
+
+/// Apply the given BPS patch to the given original file, returning the
+/// modified file.  The checksums of the patch, the original file and the
+/// modified file are all verified.
+pub fn apply_bps(
+    original: &[u8],
+    patch: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    if !patch.starts_with(BPS_HEADER) || patch.len() < BPS_HEADER.len() + 12 {
+        return Err(anyhow!("not a BPS patch"));
+    }
+    let checksums = patch.len() - 12;
+    let checksum = |offset: usize| {
+        let bytes = &patch[checksums + offset..checksums + offset + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    if crc32(&patch[..patch.len() - 4]) != checksum(8) {
+        return Err(anyhow!("BPS patch is corrupt (bad patch checksum)"));
+    }
+    let mut reader = BpsReader {
+        patch: &patch[..checksums],
+        offset: BPS_HEADER.len(),
+    };
+    let original_len = reader.number()?;
+    let modified_len = reader.number()?;
+    let metadata_len = reader.number()?;
+    reader.bytes(metadata_len)?;
+    if original_len != original.len() || crc32(original) != checksum(0) {
+        return Err(anyhow!(
+            "BPS patch is for a different file ({} bytes, CRC32 {:08X})",
+            original_len,
+            checksum(0)
+        ));
+    }
+    // The length comes from the patch, so it is not trusted to reserve
+    // memory beyond what the patch could plausibly produce.
+    let mut modified =
+        Vec::with_capacity(modified_len.min(original.len() + patch.len()));
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    while reader.offset < reader.patch.len() {
+        let command = reader.number()?;
+        let len = (command >> 2) + 1;
+        if modified.len() + len > modified_len {
+            return Err(anyhow!("BPS patch writes past the end of the file"));
+        }
+        match command & 3 {
+            0 => {
+                let start = modified.len();
+                modified.extend_from_slice(
+                    original.get(start..start + len).ok_or_else(|| {
+                        anyhow!("BPS patch reads past the end of the file")
+                    })?,
+                );
+            },
+            1 => modified.extend_from_slice(reader.bytes(len)?),
+            2 => {
+                source_offset = reader.relative_offset(source_offset)?;
+                modified.extend_from_slice(
+                    original
+                        .get(source_offset..source_offset + len)
+                        .ok_or_else(|| {
+                            anyhow!("BPS patch reads past the end of the file")
+                        })?,
+                );
+                source_offset += len;
+            },
+            _ => {
+                target_offset = reader.relative_offset(target_offset)?;
+                // The copy may overlap the bytes it produces, so it must be
+                // made one byte at a time.
+                for _ in 0..len {
+                    let byte =
+                        *modified.get(target_offset).ok_or_else(|| {
+                            anyhow!("BPS patch copies bytes not yet written")
+                        })?;
+                    modified.push(byte);
+                    target_offset += 1;
+                }
+            },
+        }
+    }
+    if modified.len() != modified_len || crc32(&modified) != checksum(4) {
+        return Err(anyhow!("BPS patch produced the wrong file"));
+    }
+    Ok(modified)
+}
+
+/// Apply the given IPS patch to the given original file, returning the
+/// modified file.  IPS patches carry no checksums, so applying one to the
+/// wrong file cannot be detected.
+pub fn apply_ips(
+    original: &[u8],
+    patch: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    if !patch.starts_with(IPS_HEADER) {
+        return Err(anyhow!("not an IPS patch"));
+    }
+    let truncated = || anyhow!("IPS patch is truncated");
+    let mut modified = original.to_vec();
+    let mut offset = IPS_HEADER.len();
+    loop {
+        let header = patch.get(offset..offset + 3).ok_or_else(truncated)?;
+        offset += 3;
+        if header == IPS_FOOTER {
+            break;
+        }
+        let start = be_number(header);
+        let size = patch.get(offset..offset + 2).ok_or_else(truncated)?;
+        offset += 2;
+        let size = be_number(size);
+        let data = if size == 0 {
+            // Run-length encoded record: a size and one byte to repeat.
+            let rle = patch.get(offset..offset + 3).ok_or_else(truncated)?;
+            offset += 3;
+            vec![rle[2]; be_number(&rle[..2])]
+        } else {
+            let data =
+                patch.get(offset..offset + size).ok_or_else(truncated)?;
+            offset += size;
+            data.to_vec()
+        };
+        if modified.len() < start + data.len() {
+            modified.resize(start + data.len(), 0x00);
+        }
+        modified[start..start + data.len()].copy_from_slice(&data);
+    }
+    match patch.len() - offset {
+        0 => (),
+        3 => modified.truncate(be_number(&patch[offset..])),
+        _ => return Err(anyhow!("unexpected bytes after end of IPS patch")),
+    }
+    Ok(modified)
+}
+
+/// Make a BPS patch which turns the given original file into the given
+/// modified file.  Each byte is either kept from the original file or
+/// stored in the patch, which suits files edited in place.
+pub fn make_bps(
+    original: &[u8],
+    modified: &[u8],
+) -> Vec<u8> {
+    let mut patch = BPS_HEADER.to_vec();
+    write_number(&mut patch, original.len());
+    write_number(&mut patch, modified.len());
+    write_number(&mut patch, 0);
+    let mut offset = 0;
+    while offset < modified.len() {
+        let same = |i: usize| original.get(i) == Some(&modified[i]);
+        let kept = same(offset);
+        let end = (offset..modified.len())
+            .find(|&i| same(i) != kept)
+            .unwrap_or(modified.len());
+        let len = end - offset;
+        if kept {
+            write_number(&mut patch, (len - 1) << 2);
+        } else {
+            write_number(&mut patch, ((len - 1) << 2) | 1);
+            patch.extend_from_slice(&modified[offset..end]);
+        }
+        offset = end;
+    }
+    patch.extend_from_slice(&crc32(original).to_le_bytes());
+    patch.extend_from_slice(&crc32(modified).to_le_bytes());
+    let checksum = crc32(&patch);
+    patch.extend_from_slice(&checksum.to_le_bytes());
+    patch
+}
+
+/// Make an IPS patch which turns the given original file into the given
+/// modified file.  This fails if the modified file is larger than the 16 MiB
+/// an IPS patch can describe.
+pub fn make_ips(
+    original: &[u8],
+    modified: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    if modified.len() > IPS_MAX_LEN {
+        return Err(anyhow!(
+            "IPS patches cannot describe files larger than {} bytes",
+            IPS_MAX_LEN
+        ));
+    }
+    let differs = |i: usize| original.get(i) != Some(&modified[i]);
+    let mut patch = IPS_HEADER.to_vec();
+    let mut offset = 0;
+    while let Some(mut start) = (offset..modified.len()).find(|&i| differs(i)) {
+        // Bytes which are the same are included in a record rather than
+        // starting a new one, where a new record would cost more.
+        let mut end = start + 1;
+        while end < modified.len()
+            && end - start < IPS_MAX_RECORD
+            && (end..modified.len().min(end + 6)).any(&differs)
+        {
+            end += 1;
+        }
+        if start == IPS_FOOTER_OFFSET {
+            start -= 1;
+            end = end.min(start + IPS_MAX_RECORD);
+        }
+        patch.extend_from_slice(&be_bytes(start, 3));
+        patch.extend_from_slice(&be_bytes(end - start, 2));
+        patch.extend_from_slice(&modified[start..end]);
+        offset = end;
+    }
+    patch.extend_from_slice(IPS_FOOTER);
+    if modified.len() < original.len() {
+        patch.extend_from_slice(&be_bytes(modified.len(), 3));
+    }
+    Ok(patch)
+}
+
+// Reads the numbers and data of a BPS patch.
+struct BpsReader<'a> {
+    patch: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BpsReader<'a> {
+    fn bytes(
+        &mut self,
+        len: usize,
+    ) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .patch
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| anyhow!("BPS patch is truncated"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> anyhow::Result<usize> {
+        let mut number = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.bytes(1)?[0];
+            number = usize::from(byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|value| number.checked_add(value))
+                .ok_or_else(|| anyhow!("BPS patch number too large"))?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or_else(|| anyhow!("BPS patch number too large"))?;
+            number = number
+                .checked_add(shift)
+                .ok_or_else(|| anyhow!("BPS patch number too large"))?;
+        }
+    }
+
+    // Read an offset relative to the given one, in which the lowest bit is
+    // the sign.
+    fn relative_offset(
+        &mut self,
+        from: usize,
+    ) -> anyhow::Result<usize> {
+        let number = self.number()?;
+        let distance = number >> 1;
+        if number & 1 == 0 {
+            from.checked_add(distance)
+        } else {
+            from.checked_sub(distance)
+        }
+        .ok_or_else(|| anyhow!("BPS patch copies from before the file"))
+    }
+}
+
+fn be_bytes(
+    value: usize,
+    len: usize,
+) -> Vec<u8> {
+    (0..len).rev().map(|i| (value >> (i * 8)) as u8).collect()
+}
+
+fn be_number(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |number, &byte| (number << 8) | usize::from(byte))
+}
+
+fn write_number(
+    patch: &mut Vec<u8>,
+    mut number: usize,
+) {
+    loop {
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(0x80 | byte);
+            break;
+        }
+        patch.push(byte);
+        number -= 1;
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    floor_plan::FloorPlan,
+    layout::Layout,
+    warp::{
+        Warp,
+        WarpKind,
+    },
+};
+use std::collections::VecDeque;
+
+/// The number of steps needed to reach each tile of a floor plan from one
+/// starting tile, moving one tile up, down, left or right at a time, and
+/// only onto tiles which are not empty (see
+/// [`Tile::is_empty`](crate::Tile::is_empty)).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DistanceMap {
+    distances: Vec<Option<usize>>,
+}
+
+impl DistanceMap {
+    /// The number of steps needed to reach the tile at the given
+    /// coordinates, or `None` if it cannot be reached.
+    pub fn distance(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> Option<usize> {
+        if x < FloorPlan::WIDTH && y < FloorPlan::HEIGHT {
+            self.distances[y * FloorPlan::WIDTH + x]
+        } else {
+            None
+        }
+    }
+
+    /// Flood fill the given floor plan from the tile at the given
+    /// coordinates.  Nothing is reachable from a tile which is not walkable.
+    pub fn new(
+        floor_plan: &FloorPlan,
+        x: usize,
+        y: usize,
+    ) -> Self {
+        let mut distances = vec![None; FloorPlan::WIDTH * FloorPlan::HEIGHT];
+        let mut queue = VecDeque::new();
+        if is_walkable(floor_plan, x, y) {
+            distances[y * FloorPlan::WIDTH + x] = Some(0);
+            queue.push_back((x, y, 0));
+        }
+        while let Some((x, y, distance)) = queue.pop_front() {
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for &(x, y) in &neighbours {
+                if is_walkable(floor_plan, x, y)
+                    && distances[y * FloorPlan::WIDTH + x].is_none()
+                {
+                    distances[y * FloorPlan::WIDTH + x] = Some(distance + 1);
+                    queue.push_back((x, y, distance + 1));
+                }
+            }
+        }
+        Self {
+            distances,
+        }
+    }
+}
+
+/// The kinds of entity which the player may need to reach.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EntityKind {
+    Warp,
+    Chest,
+    Digimon,
+}
+
+/// One of the entities of a layout.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Entity {
+    pub kind: EntityKind,
+
+    /// Index of the entity in its list in the layout.
+    pub index: usize,
+
+    pub x: u8,
+    pub y: u8,
+}
+
+/// What the player can reach after appearing at one spawn warp of a layout.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpawnReachability {
+    /// The spawn warp the player appears at.
+    pub spawn: Warp,
+
+    /// The warps, chests and Digimon encounter points which cannot be
+    /// reached from the spawn warp.
+    pub unreachable: Vec<Entity>,
+
+    /// Each next floor and exit warp of the layout, along with the length
+    /// of the shortest path to it from the spawn warp, or `None` if it
+    /// cannot be reached.
+    pub exits: Vec<(Warp, Option<usize>)>,
+}
+
+impl SpawnReachability {
+    /// Whether the player can leave the layout from the spawn warp.
+    pub fn can_exit(&self) -> bool {
+        self.exits.iter().any(|(_, distance)| distance.is_some())
+    }
+}
+
+fn is_walkable(
+    floor_plan: &FloorPlan,
+    x: usize,
+    y: usize,
+) -> bool {
+    floor_plan.tile(x, y).is_some_and(|tile| !tile.is_empty())
+}
+
+/// Find what the player can reach from each spawn warp of the given
+/// layout, treating every tile which is not empty (see
+/// [`Tile::is_empty`](crate::Tile::is_empty))
+/// as walkable.
+pub fn analyze_layout(layout: &Layout) -> Vec<SpawnReachability> {
+    let entities = layout
+        .warps()
+        .iter()
+        .enumerate()
+        .map(|(index, warp)| Entity {
+            kind: EntityKind::Warp,
+            index,
+            x: warp.x,
+            y: warp.y,
+        })
+        .chain(layout.chests().iter().enumerate().map(|(index, chest)| {
+            Entity {
+                kind: EntityKind::Chest,
+                index,
+                x: chest.x,
+                y: chest.y,
+            }
+        }))
+        .chain(layout.digimon().iter().enumerate().map(|(index, spawn)| {
+            Entity {
+                kind: EntityKind::Digimon,
+                index,
+                x: spawn.x,
+                y: spawn.y,
+            }
+        }))
+        .collect::<Vec<_>>();
+    layout
+        .warps()
+        .iter()
+        .filter(|warp| warp.kind == WarpKind::Spawn)
+        .map(|&spawn| {
+            let distances = DistanceMap::new(
+                layout.floor_plan(),
+                usize::from(spawn.x),
+                usize::from(spawn.y),
+            );
+            let distance = |x: u8, y: u8| {
+                distances.distance(usize::from(x), usize::from(y))
+            };
+            SpawnReachability {
+                spawn,
+                unreachable: entities
+                    .iter()
+                    .filter(|entity| distance(entity.x, entity.y).is_none())
+                    .copied()
+                    .collect(),
+                exits: layout
+                    .warps()
+                    .iter()
+                    .filter(|warp| {
+                        matches!(
+                            warp.kind,
+                            WarpKind::NextFloor | WarpKind::Exit
+                        )
+                    })
+                    .map(|&warp| (warp, distance(warp.x, warp.y)))
+                    .collect(),
+            }
+        })
+        .collect()
+}
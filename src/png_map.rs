@@ -0,0 +1,185 @@
+use crate::{
+    dungeon::Dungeon,
+    floor_plan::{
+        FloorPlan,
+        Tile,
+    },
+    layout::Layout,
+    warp::WarpKind,
+};
+use anyhow::Context as _;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+type Colour = [u8; 3];
+
+/// Width and height, in pixels, of each tile in a rendered image.
+pub const TILE_SIZE: usize = 10;
+
+const IMAGE_WIDTH: usize = FloorPlan::WIDTH * TILE_SIZE;
+const IMAGE_HEIGHT: usize = FloorPlan::HEIGHT * TILE_SIZE;
+
+// Entities are drawn as a letter (matching the ASCII map) in a 5x7 pixel
+// font, black on a square of the entity's colour.
+const GLYPH_SPAWN: [u8; 7] =
+    [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110];
+const GLYPH_NEXT_FLOOR: [u8; 7] =
+    [0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001, 0b10001];
+const GLYPH_EXIT: [u8; 7] =
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111];
+const GLYPH_WARP: [u8; 7] =
+    [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010];
+const GLYPH_CHEST: [u8; 7] =
+    [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110];
+const GLYPH_TRAP: [u8; 7] =
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100];
+const GLYPH_DIGIMON: [u8; 7] =
+    [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110];
+
+fn tile_colour(tile: Tile) -> Colour {
+    match tile {
+        Tile::Empty => [24, 24, 24],
+        Tile::Room => [160, 160, 160],
+        Tile::Corridor => [112, 112, 112],
+        Tile::Water => [48, 96, 208],
+        Tile::Fire => [208, 64, 32],
+        Tile::Nature => [48, 160, 64],
+        Tile::Machine => [184, 176, 80],
+        Tile::Dark => [96, 48, 128],
+        Tile::Unknown(_) => [255, 0, 255],
+    }
+}
+
+struct Image {
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn draw_entity(
+        &mut self,
+        x: u8,
+        y: u8,
+        colour: Colour,
+        glyph: &[u8; 7],
+    ) {
+        let (x, y) = (usize::from(x), usize::from(y));
+        if x >= FloorPlan::WIDTH || y >= FloorPlan::HEIGHT {
+            return;
+        }
+        self.fill_tile(x, y, colour);
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0b10000 >> column) != 0 {
+                    self.set_pixel(
+                        x * TILE_SIZE + 2 + column,
+                        y * TILE_SIZE + 1 + row,
+                        [0, 0, 0],
+                    );
+                }
+            }
+        }
+    }
+
+    fn fill_tile(
+        &mut self,
+        x: usize,
+        y: usize,
+        colour: Colour,
+    ) {
+        for py in y * TILE_SIZE..(y + 1) * TILE_SIZE {
+            for px in x * TILE_SIZE..(x + 1) * TILE_SIZE {
+                self.set_pixel(px, py, colour);
+            }
+        }
+    }
+
+    fn new() -> Self {
+        Self {
+            pixels: vec![0; IMAGE_WIDTH * IMAGE_HEIGHT * 3],
+        }
+    }
+
+    fn set_pixel(
+        &mut self,
+        x: usize,
+        y: usize,
+        colour: Colour,
+    ) {
+        let offset = (y * IMAGE_WIDTH + x) * 3;
+        self.pixels[offset..offset + 3].copy_from_slice(&colour);
+    }
+}
+
+/// Draw the given layout as a PNG image, returning the encoded image.
+///
+/// Each tile is drawn as a square of [`TILE_SIZE`] pixels coloured by tile
+/// type.  Warps, chests, traps and Digimon are drawn over the tiles where
+/// they are placed, using the same letters as [`crate::ascii_map`].
+pub fn render_png(layout: &Layout) -> anyhow::Result<Vec<u8>> {
+    let mut image = Image::new();
+    let floor_plan = layout.floor_plan();
+    for y in 0..FloorPlan::HEIGHT {
+        for x in 0..FloorPlan::WIDTH {
+            image.fill_tile(x, y, tile_colour(floor_plan.tile(x, y).unwrap()));
+        }
+    }
+    for spawn in layout.digimon() {
+        image.draw_entity(spawn.x, spawn.y, [128, 255, 128], &GLYPH_DIGIMON);
+    }
+    for trap in layout.traps() {
+        image.draw_entity(trap.x, trap.y, [255, 64, 64], &GLYPH_TRAP);
+    }
+    for chest in layout.chests() {
+        image.draw_entity(chest.x, chest.y, [255, 215, 0], &GLYPH_CHEST);
+    }
+    for warp in layout.warps() {
+        let (colour, glyph) = match warp.kind {
+            WarpKind::Spawn => ([255, 255, 255], &GLYPH_SPAWN),
+            WarpKind::NextFloor => ([0, 255, 255], &GLYPH_NEXT_FLOOR),
+            WarpKind::Exit => ([255, 128, 0], &GLYPH_EXIT),
+            WarpKind::Unknown(_) => ([255, 160, 200], &GLYPH_WARP),
+        };
+        image.draw_entity(warp.x, warp.y, colour, glyph);
+    }
+    let mut png = Vec::new();
+    let mut encoder =
+        png::Encoder::new(&mut png, IMAGE_WIDTH as u32, IMAGE_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&image.pixels))
+        .context("encoding PNG image")?;
+    Ok(png)
+}
+
+/// Write one PNG image (see [`render_png`]) for each layout of each floor
+/// of the given dungeon into the given directory, returning the paths of
+/// the files written.  Files are named `floorFF_layoutLL.png`, where `FF`
+/// is the floor number and `LL` the layout number, both counting from 1.
+pub fn write_dungeon_pngs(
+    dungeon: &Dungeon,
+    directory: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for (i, floor) in dungeon.floors().iter().enumerate() {
+        let mut layout_ids = floor.layout_slots().to_vec();
+        layout_ids.sort_unstable();
+        layout_ids.dedup();
+        for id in layout_ids {
+            let path = directory.join(format!(
+                "floor{:02}_layout{:02}.png",
+                i + 1,
+                id.0 + 1
+            ));
+            let png = render_png(&dungeon.layouts()[id.0])
+                .context(format!("rendering layout {}", id.0 + 1))?;
+            std::fs::write(&path, png)
+                .context(format!("writing \"{}\"", path.display()))?;
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
@@ -0,0 +1,131 @@
+use anyhow::anyhow;
+use serde::{
+    de::{
+        Error as _,
+        Unexpected,
+    },
+    Deserialize,
+    Deserializer,
+};
+use std::{
+    convert::{
+        TryFrom,
+        TryInto,
+    },
+    slice::ChunksExact,
+};
+
+/// Parse a little-endian 32-bit pointer (file offset) from the front
+/// of the given slice.
+pub fn parse_ptr(raw: &[u8]) -> anyhow::Result<usize> {
+    if raw.len() < 4 {
+        return Err(anyhow!("truncated pointer"));
+    }
+    let (ptr_raw, _) = raw.split_at(std::mem::size_of::<u32>());
+    let ptr = u32::from_le_bytes(ptr_raw.try_into().unwrap());
+    Ok(ptr as usize)
+}
+
+/// Return the bytes of the file from the given pointer (file offset) to the
+/// end, failing if the pointer is past the end of the file.
+pub(crate) fn slice_from(
+    raw: &[u8],
+    ptr: usize,
+) -> anyhow::Result<&[u8]> {
+    raw.get(ptr..).ok_or_else(|| {
+        anyhow!("pointer 0x{:X} is past the end of the file", ptr)
+    })
+}
+
+/// Return the bytes at the front of the given slice up to (but not
+/// including) the first record of the given size which begins with `0xFF`,
+/// which marks the end of a list.  Other bytes of a record may be `0xFF`.
+pub fn parse_list(
+    raw: &[u8],
+    size: usize,
+) -> anyhow::Result<&[u8]> {
+    let mut i = 0;
+    loop {
+        if i >= raw.len() {
+            return Err(anyhow!("truncated list"));
+        }
+        if raw[i] == 0xFF {
+            break;
+        }
+        i += size;
+    }
+    Ok(&raw[..i])
+}
+
+/// Split the list at the front of the given slice (see [`parse_list`]) into
+/// records of the given size.
+pub fn parse_records(
+    raw: &[u8],
+    size: usize,
+) -> anyhow::Result<ChunksExact<'_, u8>> {
+    let raw = parse_list(raw, size)?;
+    if raw.len() % size != 0 {
+        return Err(anyhow!("truncated record"));
+    }
+    Ok(raw.chunks_exact(size))
+}
+
+/// Deserialize a value which is stored in four bits of a dungeon file,
+/// rejecting values too large to fit.
+pub(crate) fn deserialize_nibble<'de, D>(
+    deserializer: D
+) -> Result<u8, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = u8::deserialize(deserializer)?;
+    if value > 0x0F {
+        Err(D::Error::invalid_value(
+            Unexpected::Unsigned(u64::from(value)),
+            &"a value from 0 to 15",
+        ))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Encode a pointer (file offset) as it appears in a dungeon file.
+pub fn encode_ptr(ptr: usize) -> [u8; 4] {
+    u32::try_from(ptr).expect("pointer out of range").to_le_bytes()
+}
+
+/// Encode a list of records of the given size, followed by the record
+/// marking the end of the list (`0xFF` padded with zeros to the size of a
+/// record).  This is the inverse of [`parse_records`].
+pub fn encode_list<I, R>(
+    records: I,
+    size: usize,
+) -> Vec<u8>
+where
+    I: IntoIterator<Item = R>,
+    R: AsRef<[u8]>,
+{
+    let mut list = Vec::new();
+    for record in records {
+        list.extend_from_slice(record.as_ref());
+    }
+    list.push(0xFF);
+    list.resize(list.len() + size - 1, 0x00);
+    list
+}
+
+/// Pad the given buffer with zeros so that its length is a multiple of
+/// four, which is the alignment of every structure in a dungeon file.
+pub fn pad_to_alignment(raw: &mut Vec<u8>) {
+    let padding = (4 - raw.len() % 4) % 4;
+    raw.resize(raw.len() + padding, 0x00);
+}
+
+/// Overwrite the pointer at the given offset of the given buffer.
+pub fn write_ptr(
+    raw: &mut [u8],
+    offset: usize,
+    ptr: usize,
+) {
+    raw[offset..offset + 4].copy_from_slice(&encode_ptr(ptr));
+}
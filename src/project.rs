@@ -0,0 +1,372 @@
+use crate::{
+    ascii_map,
+    character_table::CharacterTable,
+    chest::Chest,
+    digimon_spawn::DigimonSpawn,
+    dungeon::Dungeon,
+    floor::Floor,
+    floor_plan::FloorPlan,
+    game_text::GameText,
+    layout::{
+        Layout,
+        LayoutId,
+        ListPlacements,
+    },
+    trap::{
+        Trap,
+        TrapKind,
+    },
+    warp::Warp,
+};
+use anyhow::{
+    anyhow,
+    Context as _,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::HashMap,
+    convert::{
+        TryFrom,
+        TryInto as _,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Name of the file in a project directory which lists the floors of the
+/// dungeon.
+pub const DUNGEON_FILE: &str = "dungeon.toml";
+
+// Comment written at the top of each layout file, explaining the characters
+// used to draw its floor plan.  These are the tiles part of the ASCII map
+// legend, as the floor plan has no entities drawn on it.
+fn layout_file_header() -> String {
+    let mut header = String::from("# Floor plan tiles, one character each:\n");
+    for line in ascii_map::LEGEND
+        .lines()
+        .skip_while(|&line| line != "Tiles:")
+        .skip(1)
+        .take_while(|line| line.starts_with(' '))
+    {
+        header.push_str("# ");
+        header.push_str(line);
+        header.push('\n');
+    }
+    header.push('\n');
+    header
+}
+
+#[derive(Deserialize, Serialize)]
+struct DungeonFile {
+    floors: Vec<FloorEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct FloorEntry {
+    /// Name of the floor, as written by
+    /// [`GameText::to_exact_string_with`].
+    name: String,
+
+    /// Names of the layout files (without their `.toml` extension) for the
+    /// eight layout slots of the floor.
+    layouts: Vec<String>,
+
+    unknown_word: u32,
+    unknown_data: Vec<u8>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct LayoutFile {
+    /// The rows of tiles of the floor plan (see [`FloorPlan::rows`]), one
+    /// per line.
+    floor_plan: String,
+
+    list_placements: ListPlacements,
+
+    #[serde(default)]
+    warps: Vec<Warp>,
+
+    #[serde(default)]
+    chests: Vec<Chest>,
+
+    #[serde(default)]
+    traps: Vec<TrapEntry>,
+
+    #[serde(default)]
+    digimon: Vec<DigimonSpawn>,
+}
+
+impl From<&Layout> for LayoutFile {
+    fn from(layout: &Layout) -> Self {
+        let mut floor_plan = layout.floor_plan().rows().join("\n");
+        floor_plan.push('\n');
+        Self {
+            floor_plan,
+            list_placements: layout.list_placements(),
+            warps: layout.warps().to_vec(),
+            chests: layout.chests().to_vec(),
+            traps: layout.traps().iter().map(TrapEntry::from).collect(),
+            digimon: layout.digimon().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<LayoutFile> for Layout {
+    type Error = anyhow::Error;
+
+    fn try_from(file: LayoutFile) -> Result<Self, Self::Error> {
+        let rows = file.floor_plan.lines().collect::<Vec<_>>();
+        let floor_plan =
+            FloorPlan::from_rows(&rows).context("parsing floor plan")?;
+        let traps = file
+            .traps
+            .into_iter()
+            .enumerate()
+            .map(|(i, trap)| {
+                Trap::try_from(trap).context(format!("trap {}", i + 1))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self::from_parts(
+            floor_plan,
+            file.warps,
+            file.chests,
+            traps,
+            file.digimon,
+            file.list_placements,
+        ))
+    }
+}
+
+// Traps are written with each slot as text, such as `"Green Mine"`, or
+// `"-"` for an empty slot, since TOML has no way to write a missing value.
+#[derive(Deserialize, Serialize)]
+struct TrapEntry {
+    x: u8,
+    y: u8,
+    slots: [String; 4],
+    trailer: [u8; 2],
+}
+
+impl From<&Trap> for TrapEntry {
+    fn from(trap: &Trap) -> Self {
+        Self {
+            x: trap.x,
+            y: trap.y,
+            slots: [
+                trap_slot_text(trap.slots[0]),
+                trap_slot_text(trap.slots[1]),
+                trap_slot_text(trap.slots[2]),
+                trap_slot_text(trap.slots[3]),
+            ],
+            trailer: trap.trailer,
+        }
+    }
+}
+
+impl TryFrom<TrapEntry> for Trap {
+    type Error = anyhow::Error;
+
+    fn try_from(entry: TrapEntry) -> Result<Self, Self::Error> {
+        let mut slots = [None; 4];
+        for (i, (slot, text)) in slots.iter_mut().zip(&entry.slots).enumerate()
+        {
+            *slot = parse_trap_slot(text)
+                .context(format!("parsing slot {}", i + 1))?;
+        }
+        Ok(Self {
+            x: entry.x,
+            y: entry.y,
+            slots,
+            trailer: entry.trailer,
+        })
+    }
+}
+
+// Names of trap colours and families are those of their variants, except
+// that unknown codes are written in hex, such as `0x9`.
+fn nibble_name<T>(value: T) -> String
+where
+    T: Copy + std::fmt::Debug,
+    u8: From<T>,
+{
+    let name = format!("{:?}", value);
+    if name.starts_with("Unknown") {
+        format!("0x{:X}", u8::from(value))
+    } else {
+        name
+    }
+}
+
+fn parse_nibble<T>(name: &str) -> anyhow::Result<T>
+where
+    T: From<u8> + std::fmt::Debug,
+{
+    if let Some(hex) = name.strip_prefix("0x") {
+        let code = u8::from_str_radix(hex, 16)
+            .ok()
+            .filter(|&code| code <= 0x0F)
+            .ok_or_else(|| anyhow!("bad code {:?}", name))?;
+        Ok(T::from(code))
+    } else {
+        (0..=0x0F)
+            .map(T::from)
+            .find(|value| format!("{:?}", value) == name)
+            .ok_or_else(|| anyhow!("unknown name {:?}", name))
+    }
+}
+
+fn trap_slot_text(kind: Option<TrapKind>) -> String {
+    kind.map_or_else(
+        || String::from("-"),
+        |kind| {
+            format!("{} {}", nibble_name(kind.colour), nibble_name(kind.family))
+        },
+    )
+}
+
+fn parse_trap_slot(text: &str) -> anyhow::Result<Option<TrapKind>> {
+    if text == "-" {
+        return Ok(None);
+    }
+    let mut names = text.split_whitespace();
+    match (names.next(), names.next(), names.next()) {
+        (Some(colour), Some(family), None) => Ok(Some(TrapKind {
+            colour: parse_nibble(colour).context("parsing colour")?,
+            family: parse_nibble(family).context("parsing family")?,
+        })),
+        _ => Err(anyhow!("expected colour and family but found {:?}", text)),
+    }
+}
+
+fn layout_name(id: LayoutId) -> String {
+    format!("layout{:02}", id.0 + 1)
+}
+
+/// Read a dungeon from the project in the given directory, as written by
+/// [`write_project`].  Only the layouts named by the floors in
+/// [`DUNGEON_FILE`] are read.
+pub fn read_project(directory: &Path) -> anyhow::Result<Dungeon> {
+    read_project_with(directory, CharacterTable::builtin())
+}
+
+/// Read a dungeon from the project in the given directory, as written by
+/// [`write_project_with`] using the given character table.
+pub fn read_project_with(
+    directory: &Path,
+    table: &CharacterTable,
+) -> anyhow::Result<Dungeon> {
+    let path = directory.join(DUNGEON_FILE);
+    let dungeon_file: DungeonFile = toml::from_str(
+        &std::fs::read_to_string(&path)
+            .context(format!("reading \"{}\"", path.display()))?,
+    )
+    .context(format!("parsing \"{}\"", path.display()))?;
+    let mut floors = Vec::new();
+    let mut layouts = Vec::new();
+    let mut layout_ids = HashMap::new();
+    for (i, entry) in dungeon_file.floors.into_iter().enumerate() {
+        let mut layout_slots = Vec::new();
+        for name in &entry.layouts {
+            let id = if let Some(&id) = layout_ids.get(name) {
+                id
+            } else {
+                let path = directory.join(format!("{}.toml", name));
+                let layout_file: LayoutFile = toml::from_str(
+                    &std::fs::read_to_string(&path)
+                        .context(format!("reading \"{}\"", path.display()))?,
+                )
+                .context(format!("parsing \"{}\"", path.display()))?;
+                layouts.push(
+                    Layout::try_from(layout_file)
+                        .context(format!("parsing \"{}\"", path.display()))?,
+                );
+                let id = LayoutId(layouts.len() - 1);
+                layout_ids.insert(name.clone(), id);
+                id
+            };
+            layout_slots.push(id);
+        }
+        let layout_slots = layout_slots.try_into().map_err(|_| {
+            anyhow!(
+                "floor {} has {} layouts rather than 8",
+                i + 1,
+                entry.layouts.len()
+            )
+        })?;
+        let name = GameText::from_exact_string_with(&entry.name, table)
+            .context(format!("parsing name of floor {}", i + 1))?;
+        floors.push(Floor::from_parts(
+            name,
+            entry.unknown_word,
+            layout_slots,
+            entry.unknown_data,
+        ));
+    }
+    Dungeon::from_parts(floors, layouts)
+}
+
+/// Write the given dungeon as a project in the given directory, returning
+/// the paths of the files written.  The floors are listed in
+/// [`DUNGEON_FILE`], and each layout is written to a file of its own named
+/// `layoutLL.toml`, where `LL` is the layout number counting from 1.  Each
+/// layout file holds the floor plan of the layout as rows of characters,
+/// one per tile (see [`FloorPlan::rows`]), followed by tables of its
+/// entities.
+pub fn write_project(
+    dungeon: &Dungeon,
+    directory: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    write_project_with(dungeon, directory, CharacterTable::builtin())
+}
+
+/// Write the given dungeon as a project in the given directory (see
+/// [`write_project`]), writing floor names using the given character
+/// table.
+pub fn write_project_with(
+    dungeon: &Dungeon,
+    directory: &Path,
+    table: &CharacterTable,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let dungeon_file = DungeonFile {
+        floors: dungeon
+            .floors()
+            .iter()
+            .map(|floor| FloorEntry {
+                name: floor.name().to_exact_string_with(table),
+                layouts: floor
+                    .layout_slots()
+                    .iter()
+                    .map(|&id| layout_name(id))
+                    .collect(),
+                unknown_word: floor.unknown_word(),
+                unknown_data: floor.unknown_data().to_vec(),
+            })
+            .collect(),
+    };
+    let path = directory.join(DUNGEON_FILE);
+    std::fs::write(
+        &path,
+        toml::to_string(&dungeon_file).context("serializing floors")?,
+    )
+    .context(format!("writing \"{}\"", path.display()))?;
+    paths.push(path);
+    for (index, layout) in dungeon.layouts().iter().enumerate() {
+        let path =
+            directory.join(format!("{}.toml", layout_name(LayoutId(index))));
+        let mut toml = layout_file_header();
+        toml.push_str(
+            &toml::to_string(&LayoutFile::from(layout))
+                .context(format!("serializing layout {}", index + 1))?,
+        );
+        std::fs::write(&path, toml)
+            .context(format!("writing \"{}\"", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,331 @@
+use crate::{
+    chest::Chest,
+    digimon_spawn::DigimonSpawn,
+    dungeon::Dungeon,
+    floor::Floor,
+    layout::Layout,
+    pointers::{
+        encode_list,
+        parse_ptr,
+        write_ptr,
+    },
+    trap::Trap,
+    warp::Warp,
+};
+use anyhow::{
+    anyhow,
+    Context as _,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ops::Range,
+};
+
+/// Round the given offset or size up to the four-byte alignment of every
+/// structure in a dungeon file.
+fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Keeps track of the unused space in a dungeon file, handing it out to
+/// structures which need to move.  Space is only considered unused once it
+/// has been released with [`FreeSpace::free`]; when no released space is
+/// big enough, the file is extended.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FreeSpace {
+    ranges: Vec<Range<usize>>,
+}
+
+impl FreeSpace {
+    /// Find room for a structure of the given size, returning its offset.
+    /// The smallest released range big enough is used, and otherwise the
+    /// given file is extended with zeros.  The offset is always aligned.
+    pub fn allocate(
+        &mut self,
+        raw: &mut Vec<u8>,
+        size: usize,
+    ) -> usize {
+        let size = align(size);
+        let best = self
+            .ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.len() >= size)
+            .min_by_key(|(_, range)| range.len())
+            .map(|(i, _)| i);
+        if let Some(i) = best {
+            let start = self.ranges[i].start;
+            self.ranges[i].start += size;
+            if self.ranges[i].is_empty() {
+                self.ranges.remove(i);
+            }
+            start
+        } else {
+            let start = align(raw.len());
+            raw.resize(start + size, 0x00);
+            start
+        }
+    }
+
+    /// Release the given range of the given file, filling it with zeros
+    /// so that no stale data is left behind.
+    pub fn free(
+        &mut self,
+        raw: &mut [u8],
+        range: Range<usize>,
+    ) {
+        raw[range.clone()].iter_mut().for_each(|byte| *byte = 0x00);
+        self.ranges.push(range);
+        self.ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for range in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => {
+                    last.end = last.end.max(range.end);
+                },
+                _ => merged.push(range),
+            }
+        }
+        self.ranges = merged;
+    }
+
+    /// The ranges of the file which have been released and not yet handed
+    /// out again, in order.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+}
+
+// A structure (name, floor plan or entity list) in a dungeon file, along
+// with the number of pointers to it.
+#[derive(Clone, Debug)]
+struct Block {
+    capacity: usize,
+    references: usize,
+}
+
+/// A dungeon file which is updated in place, rather than written again from
+/// scratch as by [`Dungeon::to_bytes`].
+///
+/// Floor names, floor plans and entity lists are overwritten where they are
+/// if they still fit in the space they occupy (including any padding
+/// following them) and are not shared with another floor or layout.
+/// Otherwise they are moved to free space (see [`FreeSpace`]) and the
+/// pointers to them updated: the name pointer of the floor table, or the
+/// floor plan, warps, chests, traps and Digimon pointers of the layout
+/// pointer table (offsets 0, 4, 8, 12 and 16 from the start of the table).
+/// Nothing else in the file is touched, so structures this crate does not
+/// understand are kept as they are.
+#[derive(Clone, Debug)]
+pub struct DungeonImage {
+    raw: Vec<u8>,
+    free_space: FreeSpace,
+    blocks: HashMap<usize, Block>,
+    floor_tables: Vec<usize>,
+    layout_tables: Vec<usize>,
+}
+
+impl DungeonImage {
+    /// The bytes of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// The space released by structures which have moved.
+    pub fn free_space(&self) -> &FreeSpace {
+        &self.free_space
+    }
+
+    /// Take the bytes of the file.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.raw
+    }
+
+    /// Take a dungeon file to be updated in place.
+    pub fn new(raw: Vec<u8>) -> anyhow::Result<Self> {
+        let (_, coverage) = Dungeon::parse_with_coverage(&raw)?;
+        let mut starts = coverage
+            .regions()
+            .iter()
+            .map(|region| region.range.start)
+            .collect::<Vec<_>>();
+        starts.sort_unstable();
+        starts.dedup();
+        let mut image = Self {
+            raw,
+            free_space: FreeSpace::default(),
+            blocks: HashMap::new(),
+            floor_tables: Vec::new(),
+            layout_tables: Vec::new(),
+        };
+        loop {
+            let floor_ptr =
+                parse_ptr(&image.raw[image.floor_tables.len() * 4..])?;
+            if floor_ptr == 0 {
+                break;
+            }
+            image.floor_tables.push(floor_ptr);
+        }
+        // Layouts are numbered in the order first found, as by the parser.
+        for table_ptr in image.floor_tables.clone() {
+            for slot in 0..8 {
+                let layout_ptr =
+                    parse_ptr(&image.raw[table_ptr + 8 + slot * 4..])?;
+                if !image.layout_tables.contains(&layout_ptr) {
+                    image.layout_tables.push(layout_ptr);
+                }
+            }
+        }
+        // The space of each name and list extends to the next structure,
+        // but no further than the padding which aligns it.
+        let regions = coverage.regions().to_vec();
+        let mut ptr_offsets =
+            image
+                .floor_tables
+                .iter()
+                .copied()
+                .chain(image.layout_tables.iter().flat_map(|&table_ptr| {
+                    (0..5).map(move |i| table_ptr + i * 4)
+                }))
+                .collect::<Vec<_>>();
+        ptr_offsets.sort_unstable();
+        for ptr_offset in ptr_offsets {
+            let ptr = parse_ptr(&image.raw[ptr_offset..])?;
+            let end = regions
+                .iter()
+                .filter(|region| region.range.start == ptr)
+                .map(|region| region.range.end)
+                .max()
+                .ok_or_else(|| anyhow!("no structure at 0x{:X}", ptr))?;
+            let next_start = starts
+                .iter()
+                .copied()
+                .find(|&start| start >= end)
+                .unwrap_or(image.raw.len());
+            let capacity = align(end).min(next_start) - ptr;
+            image
+                .blocks
+                .entry(ptr)
+                .or_insert(Block {
+                    capacity,
+                    references: 0,
+                })
+                .references += 1;
+        }
+        Ok(image)
+    }
+
+    // Put the given bytes where the pointer at `ptr_offset` points, if they
+    // fit and nothing else points there, or otherwise somewhere they fit,
+    // updating the pointer.
+    fn place(
+        &mut self,
+        ptr_offset: usize,
+        bytes: &[u8],
+    ) -> anyhow::Result<()> {
+        let ptr = parse_ptr(&self.raw[ptr_offset..])?;
+        if self.raw.get(ptr..ptr + bytes.len()) == Some(bytes) {
+            return Ok(());
+        }
+        let block = self
+            .blocks
+            .get_mut(&ptr)
+            .ok_or_else(|| anyhow!("no structure at 0x{:X}", ptr))?;
+        if block.references == 1 && bytes.len() <= block.capacity {
+            let capacity = block.capacity;
+            self.raw[ptr..ptr + bytes.len()].copy_from_slice(bytes);
+            self.raw[ptr + bytes.len()..ptr + capacity]
+                .iter_mut()
+                .for_each(|byte| *byte = 0x00);
+            return Ok(());
+        }
+        block.references -= 1;
+        if block.references == 0 {
+            let capacity = block.capacity;
+            self.blocks.remove(&ptr);
+            self.free_space.free(&mut self.raw, ptr..ptr + capacity);
+        }
+        let new_ptr = self.free_space.allocate(&mut self.raw, bytes.len());
+        self.raw[new_ptr..new_ptr + bytes.len()].copy_from_slice(bytes);
+        write_ptr(&mut self.raw, ptr_offset, new_ptr);
+        self.blocks.insert(new_ptr, Block {
+            capacity: align(bytes.len()),
+            references: 1,
+        });
+        Ok(())
+    }
+
+    /// Update the file to hold the given dungeon, which must have the same
+    /// floors and layout slots as the dungeon the file held to begin with.
+    pub fn update(
+        &mut self,
+        dungeon: &Dungeon,
+    ) -> anyhow::Result<()> {
+        let original = Dungeon::try_from(self.raw.as_slice())?;
+        if dungeon.floors().len() != original.floors().len()
+            || dungeon.layouts().len() != original.layouts().len()
+            || dungeon.floors().iter().zip(original.floors()).any(
+                |(floor, original)| {
+                    floor.layout_slots() != original.layout_slots()
+                },
+            )
+        {
+            return Err(anyhow!(
+                "floors and layout slots must match the original dungeon"
+            ));
+        }
+        for (i, floor) in dungeon.floors().iter().enumerate() {
+            self.update_floor(self.floor_tables[i], floor)
+                .context(format!("updating floor {}", i + 1))?;
+        }
+        for (i, layout) in dungeon.layouts().iter().enumerate() {
+            self.update_layout(self.layout_tables[i], layout)
+                .context(format!("updating layout {}", i + 1))?;
+        }
+        Ok(())
+    }
+
+    fn update_floor(
+        &mut self,
+        table_ptr: usize,
+        floor: &Floor,
+    ) -> anyhow::Result<()> {
+        self.place(table_ptr, &floor.name().to_bytes())?;
+        self.raw[table_ptr + 4..table_ptr + 8]
+            .copy_from_slice(&floor.unknown_word().to_le_bytes());
+        self.raw[table_ptr + 40..table_ptr + Floor::TABLE_SIZE]
+            .copy_from_slice(floor.unknown_data());
+        Ok(())
+    }
+
+    fn update_layout(
+        &mut self,
+        table_ptr: usize,
+        layout: &Layout,
+    ) -> anyhow::Result<()> {
+        self.place(table_ptr, &layout.floor_plan().to_bytes())?;
+        self.place(
+            table_ptr + 4,
+            &encode_list(layout.warps().iter().map(Warp::to_bytes), Warp::SIZE),
+        )?;
+        self.place(
+            table_ptr + 8,
+            &encode_list(
+                layout.chests().iter().map(Chest::to_bytes),
+                Chest::SIZE,
+            ),
+        )?;
+        self.place(
+            table_ptr + 12,
+            &encode_list(layout.traps().iter().map(Trap::to_bytes), Trap::SIZE),
+        )?;
+        self.place(
+            table_ptr + 16,
+            &encode_list(
+                layout.digimon().iter().map(DigimonSpawn::to_bytes),
+                DigimonSpawn::SIZE,
+            ),
+        )
+    }
+}
@@ -0,0 +1,137 @@
+use crate::disc::RAW_SECTOR_SIZE;
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+
+// Tables for the CRC of the EDC (polynomial 0x8001801B, reflected) and for
+// multiplication by two and division by three in the Galois field of the ECC
+// (polynomial 0x11D).
+struct Tables {
+    edc: [u32; 256],
+    ecc_f: [u8; 256],
+    ecc_b: [u8; 256],
+}
+
+static TABLES: Lazy<Tables> = Lazy::new(|| {
+    let mut tables = Tables {
+        edc: [0; 256],
+        ecc_f: [0; 256],
+        ecc_b: [0; 256],
+    };
+    for i in 0..256 {
+        let mut edc = i as u32;
+        for _ in 0..8 {
+            edc = (edc >> 1)
+                ^ if edc & 1 == 0 {
+                    0
+                } else {
+                    0xD801_8001
+                };
+        }
+        tables.edc[i] = edc;
+        let f = ((i << 1)
+            ^ if i & 0x80 == 0 {
+                0
+            } else {
+                0x11D
+            }) as u8;
+        tables.ecc_f[i] = f;
+        tables.ecc_b[i ^ usize::from(f)] = i as u8;
+    }
+    tables
+});
+
+/// Compute the error detection code (a CRC) of the given bytes.
+pub fn edc(data: &[u8]) -> u32 {
+    data.iter().fold(0, |edc, &byte| {
+        (edc >> 8) ^ TABLES.edc[((edc ^ u32::from(byte)) & 0xFF) as usize]
+    })
+}
+
+// Compute one set of ECC parity bytes (P or Q) over `region`, which begins
+// with the header of the sector, writing them to `parity`.
+fn ecc_block(
+    region: &[u8],
+    major_count: usize,
+    minor_count: usize,
+    major_mult: usize,
+    minor_inc: usize,
+    parity: &mut [u8],
+) {
+    let size = major_count * minor_count;
+    for major in 0..major_count {
+        let mut index = (major >> 1) * major_mult + (major & 1);
+        let mut ecc_a = 0;
+        let mut ecc_b = 0;
+        for _ in 0..minor_count {
+            let byte = region[index];
+            index += minor_inc;
+            if index >= size {
+                index -= size;
+            }
+            ecc_a ^= byte;
+            ecc_b ^= byte;
+            ecc_a = TABLES.ecc_f[usize::from(ecc_a)];
+        }
+        ecc_a =
+            TABLES.ecc_b[usize::from(TABLES.ecc_f[usize::from(ecc_a)] ^ ecc_b)];
+        parity[major] = ecc_a;
+        parity[major + major_count] = ecc_a ^ ecc_b;
+    }
+}
+
+// Compute the P and Q parity of the given sector.  For Mode 2 sectors the
+// header is taken to be zero, so that the parity does not depend on where
+// the sector is.
+fn ecc(
+    sector: &mut [u8],
+    zero_header: bool,
+) {
+    let header = [sector[12], sector[13], sector[14], sector[15]];
+    if zero_header {
+        sector[12..16].copy_from_slice(&[0; 4]);
+    }
+    let mut p = [0; 172];
+    ecc_block(&sector[12..0x81C], 86, 24, 2, 86, &mut p);
+    sector[0x81C..0x8C8].copy_from_slice(&p);
+    let mut q = [0; 104];
+    ecc_block(&sector[12..0x8C8], 52, 43, 86, 88, &mut q);
+    sector[0x8C8..0x930].copy_from_slice(&q);
+    sector[12..16].copy_from_slice(&header);
+}
+
+/// Recompute the error detection (EDC) and correction (ECC) codes of the
+/// given raw sector, as described by ECMA-130, after its user data has
+/// changed.  Otherwise drives and emulators would consider the sector
+/// corrupt.  Mode 1 and Mode 2 Form 1 sectors have both; Mode 2 Form 2
+/// sectors only have an error detection code, which is optional, and so is
+/// only updated if it is not zero.
+pub fn update_codes(sector: &mut [u8]) -> anyhow::Result<()> {
+    if sector.len() != RAW_SECTOR_SIZE {
+        return Err(anyhow!(
+            "raw sector has {} bytes rather than {}",
+            sector.len(),
+            RAW_SECTOR_SIZE
+        ));
+    }
+    match sector[15] {
+        1 => {
+            let code = edc(&sector[..0x810]);
+            sector[0x810..0x814].copy_from_slice(&code.to_le_bytes());
+            sector[0x814..0x81C].copy_from_slice(&[0; 8]);
+            ecc(sector, false);
+        },
+        2 if sector[18] & 0x20 == 0 => {
+            let code = edc(&sector[16..0x818]);
+            sector[0x818..0x81C].copy_from_slice(&code.to_le_bytes());
+            ecc(sector, true);
+        },
+        2 => {
+            if sector[0x92C..] != [0; 4] {
+                let code = edc(&sector[16..0x92C]);
+                sector[0x92C..].copy_from_slice(&code.to_le_bytes());
+            }
+        },
+        mode => return Err(anyhow!("unsupported sector mode {}", mode)),
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::pointers::{
+    deserialize_nibble,
+    parse_records,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The colour of a trap, which is encoded in the upper four bits of its
+/// code.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum TrapColour {
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Red,
+
+    /// A colour code whose meaning is not known.
+    Unknown(#[serde(deserialize_with = "deserialize_nibble")] u8),
+}
+
+impl From<u8> for TrapColour {
+    fn from(code: u8) -> Self {
+        match code {
+            0x1 => Self::Yellow,
+            0x2 => Self::Green,
+            0x3 => Self::Blue,
+            0x4 => Self::Purple,
+            0x5 => Self::Red,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<TrapColour> for u8 {
+    fn from(colour: TrapColour) -> Self {
+        match colour {
+            TrapColour::Yellow => 0x1,
+            TrapColour::Green => 0x2,
+            TrapColour::Blue => 0x3,
+            TrapColour::Purple => 0x4,
+            TrapColour::Red => 0x5,
+            TrapColour::Unknown(code) => code,
+        }
+    }
+}
+
+/// The family of a trap, which is encoded in the lower four bits of its
+/// code.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum TrapFamily {
+    Swamp,
+    Spore,
+    Rock,
+    Mine,
+    BitBug,
+    EnergyBug,
+    ReturnBug,
+    MemoryBug,
+
+    /// A family code whose meaning is not known.
+    Unknown(#[serde(deserialize_with = "deserialize_nibble")] u8),
+}
+
+impl From<u8> for TrapFamily {
+    fn from(code: u8) -> Self {
+        match code {
+            0x1 => Self::Swamp,
+            0x2 => Self::Spore,
+            0x3 => Self::Rock,
+            0x4 => Self::Mine,
+            0x5 => Self::BitBug,
+            0x6 => Self::EnergyBug,
+            0x7 => Self::ReturnBug,
+            0x8 => Self::MemoryBug,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<TrapFamily> for u8 {
+    fn from(family: TrapFamily) -> Self {
+        match family {
+            TrapFamily::Swamp => 0x1,
+            TrapFamily::Spore => 0x2,
+            TrapFamily::Rock => 0x3,
+            TrapFamily::Mine => 0x4,
+            TrapFamily::BitBug => 0x5,
+            TrapFamily::EnergyBug => 0x6,
+            TrapFamily::ReturnBug => 0x7,
+            TrapFamily::MemoryBug => 0x8,
+            TrapFamily::Unknown(code) => code,
+        }
+    }
+}
+
+/// The kind of trap which may occupy one of the slots of a [`Trap`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct TrapKind {
+    pub family: TrapFamily,
+    pub colour: TrapColour,
+}
+
+impl TrapKind {
+    /// Decode a trap slot code, where `0x00` means the slot is empty.
+    pub fn from_code(code: u8) -> Option<Self> {
+        if code == 0x00 {
+            None
+        } else {
+            Some(Self {
+                family: TrapFamily::from(code & 0x0F),
+                colour: TrapColour::from(code >> 4),
+            })
+        }
+    }
+
+    /// Encode a trap slot, where `0x00` means the slot is empty.
+    pub fn to_code(kind: Option<Self>) -> u8 {
+        kind.map_or(0x00, |kind| {
+            u8::from(kind.colour) << 4 | u8::from(kind.family)
+        })
+    }
+}
+
+/// A location in a layout where a trap may appear.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Trap {
+    pub x: u8,
+    pub y: u8,
+
+    /// Each time the layout is entered, one of these four slots is picked
+    /// with equal (25%) chance.  A slot which is `None` means no trap
+    /// appears.
+    pub slots: [Option<TrapKind>; 4],
+
+    /// The last two bytes of the trap record, whose meaning is not known.
+    pub trailer: [u8; 2],
+}
+
+impl Trap {
+    /// Number of bytes each trap occupies in a dungeon file.
+    pub const SIZE: usize = 8;
+
+    /// Parse the list of traps at the front of the given slice.  Each trap
+    /// is eight bytes (`XX YY S1 S2 S3 S4 ?? ??`), and the list ends with
+    /// `0xFF`.
+    pub fn parse_list(raw: &[u8]) -> anyhow::Result<Vec<Self>> {
+        Ok(parse_records(raw, Self::SIZE)?
+            .map(|trap| Self {
+                x: trap[0],
+                y: trap[1],
+                slots: [
+                    TrapKind::from_code(trap[2]),
+                    TrapKind::from_code(trap[3]),
+                    TrapKind::from_code(trap[4]),
+                    TrapKind::from_code(trap[5]),
+                ],
+                trailer: [trap[6], trap[7]],
+            })
+            .collect())
+    }
+
+    /// Encode the trap as it appears in a dungeon file.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [
+            self.x,
+            self.y,
+            TrapKind::to_code(self.slots[0]),
+            TrapKind::to_code(self.slots[1]),
+            TrapKind::to_code(self.slots[2]),
+            TrapKind::to_code(self.slots[3]),
+            self.trailer[0],
+            self.trailer[1],
+        ]
+    }
+}
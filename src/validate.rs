@@ -0,0 +1,175 @@
+use crate::{
+    dungeon::Dungeon,
+    floor_plan::FloorPlan,
+    layout::{
+        Layout,
+        LayoutId,
+    },
+    warp::WarpKind,
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+};
+
+/// How serious a problem found by validation is.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// Something unusual, which may be intended.
+    Warning,
+
+    /// Something which breaks the game.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found by validating a layout or dungeon.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// The layout with the problem, if known.  Only [`validate_dungeon`]
+    /// knows which layout it is validating.
+    pub layout: Option<LayoutId>,
+
+    /// The coordinates of the tile where the problem is, if any.
+    pub position: Option<(u8, u8)>,
+
+    /// What the problem is.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new<M>(
+        severity: Severity,
+        position: Option<(u8, u8)>,
+        message: M,
+    ) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            severity,
+            layout: None,
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let Some(layout) = self.layout {
+            write!(f, "layout {}", layout.0 + 1)?;
+            if self.position.is_some() {
+                write!(f, " ")?;
+            }
+        }
+        if let Some((x, y)) = self.position {
+            write!(f, "({}, {})", x, y)?;
+        }
+        if self.layout.is_some() || self.position.is_some() {
+            write!(f, ": ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Check every layout of the given dungeon (see [`validate_layout`]).
+/// Each diagnostic identifies the layout it concerns.
+pub fn validate_dungeon(dungeon: &Dungeon) -> Vec<Diagnostic> {
+    dungeon
+        .layouts()
+        .iter()
+        .enumerate()
+        .flat_map(|(index, layout)| {
+            validate_layout(layout).into_iter().map(move |diagnostic| {
+                Diagnostic {
+                    layout: Some(LayoutId(index)),
+                    ..diagnostic
+                }
+            })
+        })
+        .collect()
+}
+
+/// Check the given layout for problems which would break it in the game,
+/// even though it can be encoded:
+/// * no spawn warp, so the player has nowhere to appear;
+/// * no next floor or exit warp, so the player cannot leave;
+/// * entities outside the floor plan, or on empty tiles (errors);
+/// * more than one entity on the same tile (warnings).
+pub fn validate_layout(layout: &Layout) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let warps = layout.warps();
+    if !warps.iter().any(|warp| warp.kind == WarpKind::Spawn) {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            None,
+            "no spawn warp",
+        ));
+    }
+    if !warps
+        .iter()
+        .any(|warp| matches!(warp.kind, WarpKind::NextFloor | WarpKind::Exit))
+    {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            None,
+            "no next floor or exit warp",
+        ));
+    }
+    let entities = warps
+        .iter()
+        .map(|warp| (warp.x, warp.y, "warp"))
+        .chain(layout.chests().iter().map(|chest| (chest.x, chest.y, "chest")))
+        .chain(layout.traps().iter().map(|trap| (trap.x, trap.y, "trap")))
+        .chain(
+            layout.digimon().iter().map(|spawn| (spawn.x, spawn.y, "Digimon")),
+        );
+    let mut occupants = HashMap::new();
+    for (x, y, entity) in entities {
+        let position = Some((x, y));
+        match layout.floor_plan().tile(usize::from(x), usize::from(y)) {
+            None => diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                position,
+                format!(
+                    "{} outside the {}x{} floor plan",
+                    entity,
+                    FloorPlan::WIDTH,
+                    FloorPlan::HEIGHT
+                ),
+            )),
+            Some(tile) if tile.is_empty() => diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                position,
+                format!("{} on an empty tile", entity),
+            )),
+            Some(_) => (),
+        }
+        if let Some(other) = occupants.insert((x, y), entity) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                position,
+                format!("{} on the same tile as a {}", entity, other),
+            ));
+        }
+    }
+    diagnostics
+}
@@ -0,0 +1,73 @@
+use crate::pointers::parse_records;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The kinds of warps a layout can have.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum WarpKind {
+    /// Where the player appears upon entering the floor.
+    Spawn,
+
+    /// Takes the player to the next floor of the dungeon.
+    NextFloor,
+
+    /// Takes the player out of the dungeon.
+    Exit,
+
+    /// A warp type code whose meaning is not known.
+    Unknown(u8),
+}
+
+impl From<u8> for WarpKind {
+    fn from(code: u8) -> Self {
+        match code {
+            0x00 => Self::Spawn,
+            0x01 => Self::NextFloor,
+            0x02 => Self::Exit,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<WarpKind> for u8 {
+    fn from(kind: WarpKind) -> Self {
+        match kind {
+            WarpKind::Spawn => 0x00,
+            WarpKind::NextFloor => 0x01,
+            WarpKind::Exit => 0x02,
+            WarpKind::Unknown(code) => code,
+        }
+    }
+}
+
+/// A location in a layout where the player is placed or can leave.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Warp {
+    pub x: u8,
+    pub y: u8,
+    pub kind: WarpKind,
+}
+
+impl Warp {
+    /// Number of bytes each warp occupies in a dungeon file.
+    pub const SIZE: usize = 3;
+
+    /// Parse the list of warps at the front of the given slice.  Each warp
+    /// is three bytes (`XX YY TYPE`), and the list ends with `0xFF`.
+    pub fn parse_list(raw: &[u8]) -> anyhow::Result<Vec<Self>> {
+        Ok(parse_records(raw, Self::SIZE)?
+            .map(|warp| Self {
+                x: warp[0],
+                y: warp[1],
+                kind: WarpKind::from(warp[2]),
+            })
+            .collect())
+    }
+
+    /// Encode the warp as it appears in a dungeon file.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [self.x, self.y, u8::from(self.kind)]
+    }
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{
+    data_file,
+    DUNGEON_FILES,
+};
+use digimon::analysis::{
+    floor_word_report,
+    WordTarget,
+};
+
+#[test]
+fn floor_words_are_not_pointers() {
+    for name in &DUNGEON_FILES {
+        for report in floor_word_report(&data_file(name)).unwrap() {
+            assert!(
+                !report.target.is_likely_pointer(report.value),
+                "{} floor {}",
+                name,
+                report.floor
+            );
+        }
+    }
+}
+
+#[test]
+fn floor_word_of_last_scsi_floor() {
+    let reports = floor_word_report(&data_file("DUNG4000.BIN")).unwrap();
+    assert_eq!(
+        vec![0, 0, 0, 0xD00],
+        reports.iter().map(|report| report.value).collect::<Vec<_>>()
+    );
+    assert_eq!(WordTarget::Null, reports[0].target);
+}
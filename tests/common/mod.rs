@@ -0,0 +1,30 @@
+// Helpers shared by the integration tests.  Each test uses only some of
+// them.
+#![allow(dead_code)]
+
+use digimon::{
+    Dungeon,
+    Layout,
+};
+use std::{
+    convert::TryFrom,
+    path::PathBuf,
+};
+
+/// The dungeon files shipped in the `data` directory.
+pub const DUNGEON_FILES: [&str; 4] =
+    ["DUNG4000.BIN", "DUNG4900.BIN", "DUNG5900.BIN", "DUNG7000.BIN"];
+
+/// Read the file with the given name from the `data` directory.
+pub fn data_file(name: &str) -> Vec<u8> {
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data").join(name);
+    std::fs::read(&path).unwrap()
+}
+
+/// Parse the dungeon file with the given name from the `data` directory and
+/// return its first layout.
+pub fn first_layout(name: &str) -> Layout {
+    let dungeon = Dungeon::try_from(&data_file(name)).unwrap();
+    dungeon.layouts()[0].clone()
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{
+    data_file,
+    DUNGEON_FILES,
+};
+use digimon::{
+    pointers::parse_ptr,
+    Dungeon,
+};
+
+#[test]
+fn only_alignment_padding_and_unknown_data_is_not_covered() {
+    for name in &DUNGEON_FILES {
+        let raw = data_file(name);
+        let (dungeon, coverage) = Dungeon::parse_with_coverage(&raw).unwrap();
+        assert_eq!(raw.len(), coverage.file_len());
+        // The part of each floor table following the layout pointers.
+        let unknown_data = (0..dungeon.floors().len())
+            .map(|i| {
+                let table_ptr = parse_ptr(&raw[i * 4..]).unwrap();
+                table_ptr + 40..table_ptr + 124
+            })
+            .collect::<Vec<_>>();
+        let gaps = coverage.gaps();
+        for range in &unknown_data {
+            assert!(gaps.contains(range), "{}: 0x{:X}", name, range.start);
+        }
+        for gap in gaps.into_iter().filter(|gap| !unknown_data.contains(gap)) {
+            assert!(gap.len() < 4, "{}: gap at 0x{:X}", name, gap.start);
+            assert!(raw[gap].iter().all(|&byte| byte == 0));
+        }
+    }
+}
+
+#[test]
+fn regions_are_described() {
+    let raw = data_file("DUNG4000.BIN");
+    let (_, coverage) = Dungeon::parse_with_coverage(&raw).unwrap();
+    let descriptions = coverage
+        .regions()
+        .iter()
+        .map(|region| region.description.as_str())
+        .collect::<Vec<_>>();
+    assert!(descriptions.contains(&"floor pointer table"));
+    assert!(descriptions.contains(&"floor 1 name"));
+    assert!(descriptions.contains(&"floor 1 layout 1 floor plan"));
+    assert!(descriptions.contains(&"floor 2 table"));
+}
@@ -0,0 +1,426 @@
+mod common;
+
+use common::data_file;
+use digimon::{
+    disc::SectorFormat,
+    sector,
+    Disc,
+    Dungeon,
+    LayoutId,
+};
+use std::{
+    convert::TryFrom,
+    io::Cursor,
+};
+
+const DUNGEONS: [&str; 2] = ["DUNG4000.BIN", "DUNG4900.BIN"];
+
+fn directory_record(
+    name: &[u8],
+    lba: usize,
+    len: usize,
+    is_directory: bool,
+) -> Vec<u8> {
+    let mut record = vec![0; 33 + name.len() + (name.len() + 1) % 2];
+    record[0] = record.len() as u8;
+    record[2..6].copy_from_slice(&(lba as u32).to_le_bytes());
+    record[6..10].copy_from_slice(&(lba as u32).to_be_bytes());
+    record[10..14].copy_from_slice(&(len as u32).to_le_bytes());
+    record[14..18].copy_from_slice(&(len as u32).to_be_bytes());
+    record[25] = if is_directory {
+        0x02
+    } else {
+        0x00
+    };
+    record[32] = name.len() as u8;
+    record[33..33 + name.len()].copy_from_slice(name);
+    record
+}
+
+fn directory(
+    lba: usize,
+    parent: usize,
+    entries: &[Vec<u8>],
+) -> Vec<u8> {
+    let mut sector = directory_record(&[0], lba, 2048, true);
+    sector.extend(directory_record(&[1], parent, 2048, true));
+    for entry in entries {
+        sector.extend(entry);
+    }
+    sector.resize(2048, 0);
+    sector
+}
+
+// Build an ISO image holding a text file in the root directory, and two of
+// the shipped dungeon files in a `DUNG` directory.
+fn build_iso() -> Vec<u8> {
+    build_iso_with(false)
+}
+
+// Build the image of `build_iso`, optionally with a second, Joliet directory
+// tree, whose names are UCS-2.
+fn build_iso_with(joliet: bool) -> Vec<u8> {
+    let text = b"Not a dungeon\n".to_vec();
+    let dungeons =
+        DUNGEONS.iter().map(|name| data_file(name)).collect::<Vec<_>>();
+    let root_lba = if joliet {
+        19
+    } else {
+        18
+    };
+    let joliet_root_lba = root_lba + 2;
+    let text_lba = if joliet {
+        root_lba + 4
+    } else {
+        root_lba + 2
+    };
+    let mut files = vec![(text_lba, text.clone())];
+    let mut lba = text_lba + 1;
+    for dungeon in &dungeons {
+        files.push((lba, dungeon.clone()));
+        lba += dungeon.len().div_ceil(2048);
+    }
+    let mut iso = vec![0; lba * 2048];
+
+    let mut descriptors = vec![(1, root_lba)];
+    if joliet {
+        descriptors.push((2, joliet_root_lba));
+    }
+    for (i, (kind, root)) in descriptors.into_iter().enumerate() {
+        let descriptor = &mut iso[(16 + i) * 2048..(17 + i) * 2048];
+        descriptor[0] = kind;
+        descriptor[1..6].copy_from_slice(b"CD001");
+        descriptor[6] = 1;
+        if kind == 2 {
+            descriptor[88..91].copy_from_slice(b"%/E");
+        }
+        descriptor[128..130].copy_from_slice(&2048u16.to_le_bytes());
+        descriptor[130..132].copy_from_slice(&2048u16.to_be_bytes());
+        descriptor[156..190].copy_from_slice(&directory_record(
+            &[0],
+            root,
+            2048,
+            true,
+        ));
+    }
+    let terminator = root_lba - 1;
+    let terminator = &mut iso[terminator * 2048..(terminator + 1) * 2048];
+    terminator[0] = 255;
+    terminator[1..6].copy_from_slice(b"CD001");
+    terminator[6] = 1;
+
+    let ucs2 = |name: &str| {
+        name.encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<_>>()
+    };
+    let mut trees =
+        vec![(root_lba, b"DUNG".to_vec(), b"README.TXT;1".to_vec())];
+    if joliet {
+        trees.push((joliet_root_lba, ucs2("DUNG"), ucs2("README.TXT;1")));
+    }
+    for (root, dung, readme) in trees {
+        let directory_lba = root + 1;
+        let root_sector = directory(root, root, &[
+            directory_record(&dung, directory_lba, 2048, true),
+            directory_record(&readme, text_lba, text.len(), false),
+        ]);
+        iso[root * 2048..(root + 1) * 2048].copy_from_slice(&root_sector);
+        let entries = DUNGEONS
+            .iter()
+            .zip(&files[1..])
+            .map(|(name, (lba, contents))| {
+                let name = format!("{};1", name);
+                let name = if root == root_lba {
+                    name.into_bytes()
+                } else {
+                    ucs2(&name)
+                };
+                directory_record(&name, *lba, contents.len(), false)
+            })
+            .collect::<Vec<_>>();
+        iso[directory_lba * 2048..(directory_lba + 1) * 2048]
+            .copy_from_slice(&directory(directory_lba, root, &entries));
+    }
+    for (lba, contents) in files {
+        iso[lba * 2048..lba * 2048 + contents.len()].copy_from_slice(&contents);
+    }
+    iso
+}
+
+// Convert an ISO image into raw Mode 2 Form 1 sectors.
+fn to_raw(iso: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::new();
+    for (lba, data) in iso.chunks(2048).enumerate() {
+        let address = lba + 150;
+        let bcd = |value: usize| (((value / 10) << 4) | (value % 10)) as u8;
+        raw.extend_from_slice(&[
+            0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0x00,
+        ]);
+        raw.extend_from_slice(&[
+            bcd(address / 75 / 60),
+            bcd(address / 75 % 60),
+            bcd(address % 75),
+            2,
+        ]);
+        raw.extend_from_slice(&[0, 0, 0x08, 0, 0, 0, 0x08, 0]);
+        raw.extend_from_slice(data);
+        raw.resize(raw.len() + 280, 0);
+        let start = raw.len() - 2352;
+        sector::update_codes(&mut raw[start..]).unwrap();
+    }
+    raw
+}
+
+fn gf_mul_alpha(value: u8) -> u8 {
+    (value << 1)
+        ^ if value & 0x80 == 0 {
+            0
+        } else {
+            0x1D
+        }
+}
+
+// Check the error detection code of the given Mode 2 Form 1 sector with a
+// bitwise CRC, and that every P and Q codeword of its error correction code
+// has zero syndromes.
+fn check_codes(sector: &[u8]) {
+    let mut crc = 0u32;
+    for &byte in &sector[16..0x81C] {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1)
+                ^ if crc & 1 == 0 {
+                    0
+                } else {
+                    0xD801_8001
+                };
+        }
+    }
+    assert_eq!(crc, 0, "bad EDC");
+    let mut region = sector[12..0x930].to_vec();
+    region[..4].copy_from_slice(&[0; 4]);
+    let check = |codeword: Vec<u8>| {
+        let sum = codeword.iter().fold(0, |sum, &byte| sum ^ byte);
+        let weighted = codeword
+            .iter()
+            .fold(0, |weighted, &byte| gf_mul_alpha(weighted) ^ byte);
+        assert_eq!((sum, weighted), (0, 0), "bad ECC");
+    };
+    for major in 0..86 {
+        check((0..26).map(|minor| region[major + 86 * minor]).collect());
+    }
+    for major in 0..52 {
+        let start = (major >> 1) * 86 + (major & 1);
+        let mut codeword = (0..43)
+            .map(|minor| region[(start + 88 * minor) % 2236])
+            .collect::<Vec<_>>();
+        codeword.push(region[2236 + major]);
+        codeword.push(region[2236 + 52 + major]);
+        check(codeword);
+    }
+}
+
+fn check_disc(
+    mut disc: Disc<Cursor<Vec<u8>>>,
+    format: SectorFormat,
+) {
+    assert_eq!(disc.format(), format);
+    let paths = disc
+        .files()
+        .unwrap()
+        .into_iter()
+        .map(|file| file.path)
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec![
+        "README.TXT",
+        "DUNG/DUNG4000.BIN",
+        "DUNG/DUNG4900.BIN"
+    ]);
+    let dungeon_files = disc.dungeon_files().unwrap();
+    assert_eq!(dungeon_files.len(), 2);
+    for (file, name) in dungeon_files.iter().zip(&DUNGEONS) {
+        assert_eq!(file.name(), *name);
+        assert_eq!(disc.read_file(file).unwrap(), data_file(name));
+    }
+    let file = disc.dungeon_file("dung4900.bin").unwrap();
+    assert_eq!(
+        disc.load_dungeon(&file).unwrap(),
+        Dungeon::try_from(&data_file("DUNG4900.BIN")).unwrap()
+    );
+    assert!(disc.dungeon_file("DUNG5900.BIN").is_err());
+}
+
+#[test]
+fn reads_dungeons_from_iso() {
+    let disc = Disc::new(Cursor::new(build_iso())).unwrap();
+    check_disc(disc, SectorFormat::Cooked);
+}
+
+#[test]
+fn reads_dungeons_from_raw_sectors() {
+    let disc = Disc::new(Cursor::new(to_raw(&build_iso()))).unwrap();
+    check_disc(disc, SectorFormat::Raw);
+}
+
+#[test]
+fn opens_image_named_by_cue_sheet() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("GAME.BIN"), to_raw(&build_iso())).unwrap();
+    std::fs::write(
+        dir.path().join("GAME.CUE"),
+        "FILE \"GAME.BIN\" BINARY\n  TRACK 01 MODE2/2352\n    INDEX 01 \
+         00:00:00\n",
+    )
+    .unwrap();
+    let mut disc = Disc::open(dir.path().join("GAME.CUE")).unwrap();
+    assert_eq!(disc.format(), SectorFormat::Raw);
+    assert_eq!(disc.dungeon_files().unwrap().len(), 2);
+}
+
+#[test]
+fn rejects_image_without_file_system() {
+    let mut disc = Disc::new(Cursor::new(vec![0; 20 * 2048])).unwrap();
+    assert!(disc.files().is_err());
+}
+
+#[test]
+fn rejects_file_longer_than_image() {
+    let mut iso = build_iso();
+    let name =
+        iso.windows(12).position(|window| window == b"README.TXT;1").unwrap();
+    let record = name - 33;
+    iso[record + 10..record + 14].copy_from_slice(&u32::MAX.to_le_bytes());
+    iso[record + 14..record + 18].copy_from_slice(&u32::MAX.to_be_bytes());
+    let mut disc = Disc::new(Cursor::new(iso)).unwrap();
+    let file = disc.files().unwrap().remove(0);
+    assert_eq!(file.len, u32::MAX);
+    assert!(disc.read_file(&file).is_err());
+}
+
+fn edited_dungeon(chests: usize) -> Dungeon {
+    let mut dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let layout = dungeon.layout_mut(LayoutId(0)).unwrap();
+    for i in 0..chests {
+        layout.add_chest(10 + i as u8, 10, 0x01, 0x01).unwrap();
+    }
+    dungeon
+}
+
+#[test]
+fn replaces_dungeon_in_place() {
+    let dungeon = edited_dungeon(1);
+    for raw in &[false, true] {
+        let image = if *raw {
+            to_raw(&build_iso())
+        } else {
+            build_iso()
+        };
+        let len = image.len();
+        let mut disc = Disc::new(Cursor::new(image)).unwrap();
+        let file = disc.dungeon_file("DUNG4000.BIN").unwrap();
+        let replaced = disc.replace_dungeon(&file, &dungeon).unwrap();
+        assert_eq!(replaced.lba, file.lba);
+        assert_eq!(replaced.len as usize, dungeon.to_bytes().len());
+
+        let mut disc = Disc::new(disc.into_inner()).unwrap();
+        assert_eq!(disc.dungeon_file("DUNG4000.BIN").unwrap(), replaced);
+        assert_eq!(disc.load_dungeon(&replaced).unwrap(), dungeon);
+        let other = disc.dungeon_file("DUNG4900.BIN").unwrap();
+        assert_eq!(disc.read_file(&other).unwrap(), data_file("DUNG4900.BIN"));
+
+        let image = disc.into_inner().into_inner();
+        assert_eq!(image.len(), len);
+        if *raw {
+            for sector in image.chunks(2352) {
+                check_codes(sector);
+            }
+        }
+    }
+}
+
+#[test]
+fn refuses_dungeon_which_does_not_fit() {
+    let dungeon = edited_dungeon(20);
+    let image = to_raw(&build_iso());
+    let mut disc = Disc::new(Cursor::new(image.clone())).unwrap();
+    let file = disc.dungeon_file("DUNG4000.BIN").unwrap();
+    assert!(disc.replace_dungeon(&file, &dungeon).is_err());
+    assert_eq!(disc.into_inner().into_inner(), image);
+}
+
+#[test]
+fn sector_codes_are_consistent() {
+    let mut sector = to_raw(&[0; 2048]);
+    for (i, byte) in sector[24..24 + 2048].iter_mut().enumerate() {
+        *byte = (i * 7 + i / 256) as u8;
+    }
+    sector::update_codes(&mut sector).unwrap();
+    check_codes(&sector);
+}
+
+#[test]
+fn shrinking_keeps_allocated_sectors() {
+    let mut disc = Disc::new(Cursor::new(build_iso())).unwrap();
+    let file = disc.dungeon_file("DUNG4000.BIN").unwrap();
+    let sectors = file.sectors();
+    assert_eq!(sectors, data_file("DUNG4000.BIN").len().div_ceil(2048));
+    let shrunk = disc.replace_file(&file, b"small").unwrap();
+    assert_eq!(shrunk.len, 5);
+    assert_eq!(shrunk.sectors(), sectors);
+    let dungeon = edited_dungeon(1);
+    disc.replace_dungeon(&shrunk, &dungeon).unwrap();
+    let mut disc = Disc::new(disc.into_inner()).unwrap();
+    let file = disc.dungeon_file("DUNG4000.BIN").unwrap();
+    assert_eq!(disc.load_dungeon(&file).unwrap(), dungeon);
+}
+
+#[test]
+fn only_the_extent_of_the_file_is_written() {
+    // Free space after the last file, which the game could still read by
+    // sector.
+    let mut iso = build_iso();
+    let free = iso.len();
+    iso.resize(free + 2048, 0xAA);
+    let volume_space = (iso.len() / 2048) as u32;
+    iso[16 * 2048 + 80..16 * 2048 + 84]
+        .copy_from_slice(&volume_space.to_le_bytes());
+    let mut disc = Disc::new(Cursor::new(iso)).unwrap();
+    let file = disc.dungeon_file("DUNG4900.BIN").unwrap();
+    assert_eq!(file.sectors(), data_file("DUNG4900.BIN").len().div_ceil(2048));
+    let mut grown = data_file("DUNG4900.BIN");
+    grown.resize(file.sectors() * 2048 + 1, 0);
+    assert!(disc.replace_file(&file, &grown).is_err());
+    disc.replace_file(&file, b"small").unwrap();
+    let image = disc.into_inner().into_inner();
+    assert!(image[free..].iter().all(|&byte| byte == 0xAA));
+}
+
+#[test]
+fn nothing_is_written_if_a_sector_is_form_2() {
+    let mut disc = Disc::new(Cursor::new(to_raw(&build_iso()))).unwrap();
+    let file = disc.dungeon_file("DUNG4000.BIN").unwrap();
+    let mut image = disc.into_inner().into_inner();
+    // Mark the last sector of the file as Mode 2 Form 2 in both copies of
+    // its subheader.
+    let last = (file.lba as usize + file.sectors() - 1) * 2352;
+    image[last + 18] |= 0x20;
+    image[last + 22] |= 0x20;
+    let mut disc = Disc::new(Cursor::new(image.clone())).unwrap();
+    assert!(disc.replace_dungeon(&file, &edited_dungeon(1)).is_err());
+    assert_eq!(disc.into_inner().into_inner(), image);
+}
+
+#[test]
+fn replacing_updates_joliet_records() {
+    let mut disc = Disc::new(Cursor::new(build_iso_with(true))).unwrap();
+    assert_eq!(disc.dungeon_files().unwrap().len(), 2);
+    let file = disc.dungeon_file("DUNG4000.BIN").unwrap();
+    let replaced = disc.replace_dungeon(&file, &edited_dungeon(1)).unwrap();
+    assert_ne!(replaced.len, file.len);
+    let image = disc.into_inner().into_inner();
+    // The first record after `.` and `..` of the Joliet `DUNG` directory.
+    let record = &image[22 * 2048 + 68..];
+    assert_eq!(&record[2..6], &file.lba.to_le_bytes());
+    assert_eq!(&record[10..14], &replaced.len.to_le_bytes());
+    assert_eq!(&record[14..18], &replaced.len.to_be_bytes());
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::data_file;
+use digimon::{
+    Dungeon,
+    DungeonImage,
+    Encounter,
+    LayoutId,
+    Tile,
+    TrapColour,
+    TrapFamily,
+    TrapKind,
+    WarpKind,
+};
+use std::convert::TryFrom;
+
+#[test]
+fn edits_survive_encoding() {
+    let raw = data_file("DUNG4900.BIN");
+    let mut dungeon = Dungeon::try_from(&raw).unwrap();
+    assert!(!dungeon.is_dirty());
+    let layout = dungeon.layout_mut(LayoutId(0)).unwrap();
+    let chest = layout.add_chest(10, 12, 0x2A, 0x03).unwrap();
+    let trap = layout
+        .add_trap(11, 12, [
+            None,
+            Some(TrapKind {
+                family: TrapFamily::Mine,
+                colour: TrapColour::Green,
+            }),
+            None,
+            None,
+        ])
+        .unwrap();
+    let spawn = layout
+        .add_digimon(12, 12, [
+            Encounter {
+                group: 1,
+                chance: 1,
+            },
+            Encounter {
+                group: 2,
+                chance: 1,
+            },
+        ])
+        .unwrap();
+    layout.move_warp(0, 5, 6).unwrap();
+    let removed = layout.traps()[0];
+    layout.remove_trap(0).unwrap();
+    layout.floor_plan_mut().set_tile(5, 6, Tile::Water).unwrap();
+    assert!(dungeon.is_dirty());
+
+    let layout = &dungeon.layouts()[0];
+    assert_eq!(layout.chests()[chest].item, 0x2A);
+    assert_eq!(layout.traps()[trap - 1].x, 11);
+    assert_eq!(layout.digimon()[spawn].y, 12);
+    assert!(!layout.traps().contains(&removed));
+    assert_eq!(layout.floor_plan().tile(5, 6), Some(Tile::Water));
+
+    let reparsed = Dungeon::try_from(&dungeon.to_bytes()).unwrap();
+    assert_eq!(reparsed, dungeon);
+    let mut image = DungeonImage::new(raw).unwrap();
+    image.update(&dungeon).unwrap();
+    let patched = Dungeon::try_from(image.as_bytes()).unwrap();
+    assert_eq!(patched.layouts()[0].traps(), dungeon.layouts()[0].traps());
+    assert_eq!(patched.layouts()[0].chests(), dungeon.layouts()[0].chests());
+
+    dungeon.mark_clean();
+    assert!(!dungeon.is_dirty());
+}
+
+#[test]
+fn edits_are_bounds_checked() {
+    let mut dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let layout = dungeon.layout_mut(LayoutId(0)).unwrap();
+    let before = layout.clone();
+    assert!(layout.add_chest(64, 0, 0, 0).is_err());
+    assert!(layout.add_warp(0, 48, WarpKind::Exit).is_err());
+    assert!(layout.move_warp(0, 200, 3).is_err());
+    assert!(layout.move_warp(layout.warps().len(), 3, 3).is_err());
+    assert!(layout.remove_trap(layout.traps().len()).is_err());
+    assert!(layout.floor_plan_mut().set_tile(64, 0, Tile::Water).is_err());
+    assert_eq!(*layout, before);
+    assert!(dungeon.layout_mut(LayoutId(1000)).is_none());
+}
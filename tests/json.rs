@@ -0,0 +1,107 @@
+mod common;
+
+use common::data_file;
+use digimon::{
+    CharacterTable,
+    Dungeon,
+    Encounter,
+    GameText,
+    Tile,
+    TrapColour,
+    TrapFamily,
+};
+use std::convert::TryFrom;
+
+fn assert_json_round_trip(name: &str) {
+    let raw = data_file(name);
+    let dungeon = Dungeon::try_from(&raw).unwrap();
+    let json = serde_json::to_string(&dungeon).unwrap();
+    let imported: Dungeon = serde_json::from_str(&json).unwrap();
+    assert_eq!(dungeon, imported);
+    assert!(raw == imported.to_bytes(), "{} differs after import", name);
+}
+
+#[test]
+fn dung4000_json_round_trip() {
+    assert_json_round_trip("DUNG4000.BIN");
+}
+
+#[test]
+fn dung4900_json_round_trip() {
+    assert_json_round_trip("DUNG4900.BIN");
+}
+
+#[test]
+fn dung5900_json_round_trip() {
+    assert_json_round_trip("DUNG5900.BIN");
+}
+
+#[test]
+fn dung7000_json_round_trip() {
+    assert_json_round_trip("DUNG7000.BIN");
+}
+
+#[test]
+fn json_keeps_names_and_tiles() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let json = serde_json::to_value(&dungeon).unwrap();
+    assert_eq!("SCSI Domain1F", json["floors"][0]["name"]);
+    let rows = json["layouts"][0]["floor_plan"].as_array().unwrap();
+    assert_eq!(48, rows.len());
+    assert_eq!(64, rows[0].as_str().unwrap().chars().count());
+}
+
+#[test]
+fn json_with_bad_layout_index_is_rejected() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let mut json = serde_json::to_value(&dungeon).unwrap();
+    json["floors"][0]["layout_slots"][0] = 1000.into();
+    assert!(serde_json::from_value::<Dungeon>(json).is_err());
+}
+
+#[test]
+fn json_with_values_too_large_for_nibbles_is_rejected() {
+    let encounter = r#"{"group": 1, "chance": 16}"#;
+    assert!(serde_json::from_str::<Encounter>(encounter).is_err());
+    let encounter = r#"{"group": 16, "chance": 1}"#;
+    assert!(serde_json::from_str::<Encounter>(encounter).is_err());
+    assert!(serde_json::from_str::<Encounter>(
+        r#"{"group": 15, "chance": 15}"#
+    )
+    .is_ok());
+    assert!(serde_json::from_str::<TrapColour>(r#"{"Unknown": 16}"#).is_err());
+    assert!(serde_json::from_str::<TrapFamily>(r#"{"Unknown": 16}"#).is_err());
+    assert!(serde_json::from_str::<Tile>(r#"{"Unknown": 16}"#).is_err());
+    assert_eq!(
+        serde_json::from_str::<Tile>(r#"{"Unknown": 9}"#).unwrap(),
+        Tile::Unknown(9)
+    );
+}
+
+#[test]
+fn json_with_known_tile_codes_as_unknown_is_rejected() {
+    assert!(serde_json::from_str::<Tile>(r#"{"Unknown": 0}"#).is_err());
+    assert!(serde_json::from_str::<Tile>(r#"{"Unknown": 8}"#).is_err());
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let mut json = serde_json::to_value(&dungeon).unwrap();
+    let row = json["layouts"][0]["floor_plan"][0].as_str().unwrap();
+    let row = format!("0{}", &row[1..]);
+    json["layouts"][0]["floor_plan"][0] = row.into();
+    assert!(serde_json::from_value::<Dungeon>(json).is_err());
+}
+
+#[test]
+fn json_names_use_given_character_table() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let mut table = CharacterTable::builtin().clone();
+    table.merge(&CharacterTable::from_tbl("1C=$").unwrap());
+    let json = GameText::with_serde_table(&table, || {
+        serde_json::to_value(&dungeon).unwrap()
+    });
+    assert_eq!("$C$I Domain1F", json["floors"][0]["name"]);
+    assert!(serde_json::from_value::<Dungeon>(json.clone()).is_err());
+    let imported = GameText::with_serde_table(&table, || {
+        serde_json::from_value::<Dungeon>(json).unwrap()
+    });
+    assert_eq!(dungeon, imported);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::first_layout;
+use digimon::{
+    ascii_map,
+    Tile,
+};
+
+#[test]
+fn low_nibble_is_left_tile() {
+    // Bytes 6 and 15 of row 7 are `18` and `81`.
+    let layout = first_layout("DUNG4900.BIN");
+    let floor_plan = layout.floor_plan();
+    assert_eq!(floor_plan.tile(12, 7), Some(Tile::Empty));
+    assert_eq!(floor_plan.tile(13, 7), Some(Tile::Corridor));
+    assert_eq!(floor_plan.tile(30, 7), Some(Tile::Corridor));
+    assert_eq!(floor_plan.tile(31, 7), Some(Tile::Empty));
+}
+
+#[test]
+fn ascii_map_overlays_entities() {
+    let map = first_layout("DUNG4900.BIN").render_ascii();
+    assert!(map.ends_with(&format!("\n{}", ascii_map::LEGEND)));
+    let rows =
+        map.lines().take_while(|row| !row.is_empty()).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 48);
+    assert!(rows.iter().all(|row| row.len() == 64));
+    assert_eq!(rows[3], format!("{}:::::::{}", "#".repeat(39), "#".repeat(18)));
+    assert_eq!(&rows[7][12..32], "#:::::.S.NT::::::::#");
+    assert_eq!(&rows[10][50..56], "#====D");
+}
+
+#[test]
+fn png_map_colours_tiles_and_entities() {
+    let png = first_layout("DUNG4900.BIN").render_png().unwrap();
+    let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (640, 480));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    let pixel = |x: usize, y: usize| {
+        let offset = (y * 640 + x) * 3;
+        [pixels[offset], pixels[offset + 1], pixels[offset + 2]]
+    };
+    // Empty and corridor tiles at (12, 7) and (13, 7).
+    assert_eq!(pixel(125, 75), [24, 24, 24]);
+    assert_eq!(pixel(135, 75), [112, 112, 112]);
+    // The spawn warp at (19, 7): a white square with a black letter.
+    assert_eq!(pixel(190, 70), [255, 255, 255]);
+    assert_eq!(pixel(193, 71), [0, 0, 0]);
+    // The next floor warp at (21, 7) and the trap at (22, 7).
+    assert_eq!(pixel(210, 70), [0, 255, 255]);
+    assert_eq!(pixel(220, 70), [255, 64, 64]);
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use common::data_file;
+use digimon::{
+    patch::{
+        self,
+        PatchFormat,
+    },
+    Dungeon,
+    LayoutId,
+};
+use std::convert::TryFrom;
+
+// The shipped dungeon file with a chest added, which moves everything
+// after it.
+fn modified_dungeon(original: &[u8]) -> Vec<u8> {
+    let mut dungeon = Dungeon::try_from(original).unwrap();
+    dungeon.layout_mut(LayoutId(3)).unwrap().add_chest(1, 2, 3, 4).unwrap();
+    dungeon.to_bytes()
+}
+
+fn bps_number(
+    patch: &mut Vec<u8>,
+    mut number: usize,
+) {
+    loop {
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(0x80 | byte);
+            break;
+        }
+        patch.push(byte);
+        number -= 1;
+    }
+}
+
+#[test]
+fn patches_round_trip() {
+    let original = data_file("DUNG4900.BIN");
+    let modified = modified_dungeon(&original);
+    let cases = vec![
+        (original.clone(), modified.clone()),
+        (modified.clone(), original.clone()),
+        (original.clone(), original.clone()),
+    ];
+    for (from, to) in cases {
+        let ips = patch::make_ips(&from, &to).unwrap();
+        assert_eq!(PatchFormat::detect(&ips), Some(PatchFormat::Ips));
+        assert_eq!(patch::apply_patch(&from, &ips).unwrap(), to);
+        let bps = patch::make_bps(&from, &to);
+        assert_eq!(PatchFormat::detect(&bps), Some(PatchFormat::Bps));
+        assert_eq!(patch::apply_patch(&from, &bps).unwrap(), to);
+    }
+}
+
+#[test]
+fn ips_record_avoids_footer_offset() {
+    let original = vec![0; 0x45_4F50];
+    let mut modified = original.clone();
+    modified[0x45_4F46] = 1;
+    let ips = patch::make_ips(&original, &modified).unwrap();
+    assert!(!ips[5..ips.len() - 3].starts_with(b"EOF"));
+    assert_eq!(patch::apply_ips(&original, &ips).unwrap(), modified);
+    // A record of the maximum length starting at the offset must not grow
+    // past that length when moved back a byte.
+    let mut modified = vec![0; 0x45_4F46];
+    modified.resize(0x45_4F46 + 0x1_0000, 1);
+    let original = vec![0; modified.len()];
+    let ips = patch::make_ips(&original, &modified).unwrap();
+    assert!(!ips[5..ips.len() - 3].starts_with(b"EOF"));
+    assert_eq!(&ips[8..10], &[0xFF, 0xFF]);
+    assert_eq!(patch::apply_ips(&original, &ips).unwrap(), modified);
+    assert!(patch::make_ips(&[], &vec![1; 0x100_0001]).is_err());
+}
+
+#[test]
+fn bps_checksums_are_verified() {
+    let original = data_file("DUNG4900.BIN");
+    let modified = modified_dungeon(&original);
+    let bps = patch::make_bps(&original, &modified);
+    let other = data_file("DUNG5900.BIN");
+    assert!(patch::apply_bps(&other, &bps).is_err());
+    let mut wrong = original.clone();
+    wrong[100] ^= 1;
+    assert!(patch::apply_bps(&wrong, &bps).is_err());
+    let mut corrupt = bps.clone();
+    let middle = corrupt.len() / 2;
+    corrupt[middle] ^= 1;
+    assert!(patch::apply_bps(&original, &corrupt).is_err());
+}
+
+#[test]
+fn bps_copies_are_applied() {
+    // Turn "abcdef" into "defabcabcab": copy "def" from the source, then
+    // "abc" from the source, then five bytes from the output so far.
+    let original = b"abcdef".to_vec();
+    let modified = b"defabcabcab".to_vec();
+    let mut bps = b"BPS1".to_vec();
+    bps_number(&mut bps, original.len());
+    bps_number(&mut bps, modified.len());
+    bps_number(&mut bps, 0);
+    bps_number(&mut bps, (2 << 2) | 2);
+    bps_number(&mut bps, 3 << 1);
+    bps_number(&mut bps, (2 << 2) | 2);
+    bps_number(&mut bps, (6 << 1) | 1);
+    bps_number(&mut bps, (4 << 2) | 3);
+    bps_number(&mut bps, 3 << 1);
+    bps.extend_from_slice(&patch::crc32(&original).to_le_bytes());
+    bps.extend_from_slice(&patch::crc32(&modified).to_le_bytes());
+    let checksum = patch::crc32(&bps);
+    bps.extend_from_slice(&checksum.to_le_bytes());
+    assert_eq!(patch::apply_bps(&original, &bps).unwrap(), modified);
+}
+
+#[test]
+fn bps_numbers_which_overflow_are_rejected() {
+    let mut bps = b"BPS1".to_vec();
+    bps.extend_from_slice(&[0x7F; 12]);
+    bps.push(0x80);
+    bps.extend_from_slice(&[0; 8]);
+    let checksum = patch::crc32(&bps);
+    bps.extend_from_slice(&checksum.to_le_bytes());
+    assert!(patch::apply_bps(&[], &bps).is_err());
+}
+
+#[test]
+fn crc32_matches_standard() {
+    assert_eq!(patch::crc32(b"123456789"), 0xCBF4_3926);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{
+    data_file,
+    DUNGEON_FILES,
+};
+use digimon::{
+    pathfinding::EntityKind,
+    Dungeon,
+    Layout,
+};
+use std::convert::TryFrom;
+
+#[test]
+fn shipped_layouts_are_connected() {
+    for name in &DUNGEON_FILES {
+        let dungeon = Dungeon::try_from(&data_file(name)).unwrap();
+        for (i, layout) in dungeon.layouts().iter().enumerate() {
+            let reachability = layout.reachability();
+            assert!(!reachability.is_empty());
+            for spawn in reachability {
+                assert!(spawn.can_exit(), "{} layout {}", name, i + 1);
+                assert!(
+                    spawn.unreachable.is_empty(),
+                    "{} layout {}",
+                    name,
+                    i + 1
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn walled_off_half_is_unreachable() {
+    // Room tiles everywhere, except for a wall down the middle.
+    let row = format!("{}#{}", ".".repeat(32), ".".repeat(31));
+    assert_walled_off_half_is_unreachable(row);
+}
+
+#[test]
+fn tile_code_9_is_a_wall() {
+    let row = format!("{}9{}", ".".repeat(32), ".".repeat(31));
+    assert_walled_off_half_is_unreachable(row);
+}
+
+fn assert_walled_off_half_is_unreachable(row: String) {
+    let layout: Layout = serde_json::from_value(serde_json::json!({
+        "floor_plan": vec![row; 48],
+        "warps": [
+            { "x": 1, "y": 1, "kind": "Spawn" },
+            { "x": 5, "y": 4, "kind": "NextFloor" },
+            { "x": 40, "y": 1, "kind": "Exit" },
+        ],
+        "chests": [{ "x": 40, "y": 2, "item": 1, "rate": 1 }],
+        "traps": [],
+        "digimon": [],
+        "list_placements": {
+            "warps": "Inline",
+            "chests": "Inline",
+            "traps": "Inline",
+            "digimon": "Inline",
+        },
+    }))
+    .unwrap();
+    let reachability = layout.reachability();
+    assert_eq!(1, reachability.len());
+    let spawn = &reachability[0];
+    assert_eq!(
+        vec![Some(7), None],
+        spawn.exits.iter().map(|(_, distance)| *distance).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![(EntityKind::Warp, 2), (EntityKind::Chest, 0)],
+        spawn
+            .unreachable
+            .iter()
+            .map(|entity| (entity.kind, entity.index))
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::data_file;
+use digimon::{
+    project,
+    CharacterTable,
+    Dungeon,
+    Tile,
+};
+use std::convert::TryFrom;
+
+fn assert_project_round_trip(name: &str) {
+    let raw = data_file(name);
+    let dungeon = Dungeon::try_from(&raw).unwrap();
+    let directory = tempfile::tempdir().unwrap();
+    project::write_project(&dungeon, directory.path()).unwrap();
+    let compiled = project::read_project(directory.path()).unwrap();
+    assert_eq!(dungeon, compiled);
+    assert!(raw == compiled.to_bytes(), "{} differs after compiling", name);
+}
+
+#[test]
+fn dung4000_project_round_trip() {
+    assert_project_round_trip("DUNG4000.BIN");
+}
+
+#[test]
+fn dung4900_project_round_trip() {
+    assert_project_round_trip("DUNG4900.BIN");
+}
+
+#[test]
+fn dung5900_project_round_trip() {
+    assert_project_round_trip("DUNG5900.BIN");
+}
+
+#[test]
+fn dung7000_project_round_trip() {
+    assert_project_round_trip("DUNG7000.BIN");
+}
+
+#[test]
+fn edited_floor_plan_is_compiled() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let directory = tempfile::tempdir().unwrap();
+    project::write_project(&dungeon, directory.path()).unwrap();
+    let path = directory.path().join("layout01.toml");
+    let toml = std::fs::read_to_string(&path).unwrap();
+    let edited = toml.replacen(
+        "\n################################################################\n",
+        "\n~~~~############################################################\n",
+        1,
+    );
+    assert_ne!(toml, edited);
+    std::fs::write(&path, edited).unwrap();
+    let compiled = project::read_project(directory.path()).unwrap();
+    let floor_plan = compiled.layouts()[0].floor_plan();
+    assert_eq!(Some(Tile::Water), floor_plan.tile(3, 0));
+    assert_eq!(Some(Tile::Empty), floor_plan.tile(4, 0));
+}
+
+#[test]
+fn project_names_use_given_character_table() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let mut table = CharacterTable::builtin().clone();
+    table.merge(&CharacterTable::from_tbl("1C=$").unwrap());
+    let directory = tempfile::tempdir().unwrap();
+    project::write_project_with(&dungeon, directory.path(), &table).unwrap();
+    let toml =
+        std::fs::read_to_string(directory.path().join(project::DUNGEON_FILE))
+            .unwrap();
+    assert!(toml.contains("name = \"$C$I Domain1F\""));
+    assert!(project::read_project(directory.path()).is_err());
+    let compiled =
+        project::read_project_with(directory.path(), &table).unwrap();
+    assert_eq!(dungeon, compiled);
+}
+
+#[test]
+fn layout_files_explain_tiles_with_map_legend() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG4000.BIN")).unwrap();
+    let directory = tempfile::tempdir().unwrap();
+    project::write_project(&dungeon, directory.path()).unwrap();
+    let toml = std::fs::read_to_string(directory.path().join("layout01.toml"))
+        .unwrap();
+    let header = toml.lines().take_while(|line| line.starts_with('#'));
+    assert_eq!(
+        vec![
+            "# Floor plan tiles, one character each:",
+            "#   # empty     . room      : corridor  ~ water",
+            "#   ^ fire      \" nature    = machine   ; dark",
+            "#   7 9 A-F unknown tile code",
+        ],
+        header.collect::<Vec<_>>()
+    );
+}
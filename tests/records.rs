@@ -0,0 +1,113 @@
+mod common;
+
+use common::first_layout;
+use digimon::{
+    pointers,
+    Chest,
+    DigimonSpawn,
+    Trap,
+    TrapColour,
+    TrapFamily,
+    TrapKind,
+    Warp,
+    WarpKind,
+};
+
+#[test]
+fn chest_item_may_be_0xff() {
+    let raw = [1, 2, 0xFF, 3, 4, 5, 6, 0xFF, 0xFF, 0, 0, 0];
+    let chests = Chest::parse_list(&raw).unwrap();
+    assert_eq!(chests, vec![
+        Chest {
+            x: 1,
+            y: 2,
+            item: 0xFF,
+            rate: 3,
+        },
+        Chest {
+            x: 4,
+            y: 5,
+            item: 6,
+            rate: 0xFF,
+        },
+    ]);
+    assert_eq!(
+        pointers::encode_list(chests.iter().map(Chest::to_bytes), Chest::SIZE),
+        raw
+    );
+}
+
+#[test]
+fn encounter_groups_are_picked_evenly() {
+    let spawns = DigimonSpawn::parse_list(&[
+        1, 2, 0x11, 0x44, 3, 4, 0x12, 0x12, 5, 6, 0x21, 0x33, 0xFF, 0, 0, 0,
+    ])
+    .unwrap();
+    let probabilities = spawns
+        .iter()
+        .map(|spawn| spawn.group_probabilities().into_iter().collect())
+        .collect::<Vec<Vec<_>>>();
+    assert_eq!(probabilities, vec![
+        vec![(1, 0.5), (4, 0.5)],
+        vec![(1, 1.0)],
+        vec![(2, 0.5), (3, 0.5)],
+    ]);
+}
+
+#[test]
+fn shipped_warps_are_decoded() {
+    // The list starts `13 07 00 15 07 01`.
+    let layout = first_layout("DUNG4900.BIN");
+    assert_eq!(layout.warps()[..2], [
+        Warp {
+            x: 19,
+            y: 7,
+            kind: WarpKind::Spawn,
+        },
+        Warp {
+            x: 21,
+            y: 7,
+            kind: WarpKind::NextFloor,
+        },
+    ]);
+    assert_eq!(layout.warps()[1].to_bytes(), [0x15, 0x07, 0x01]);
+}
+
+#[test]
+fn shipped_chests_are_decoded() {
+    // The list starts `0A 10 10 32`.
+    let layout = first_layout("DUNG4900.BIN");
+    assert_eq!(layout.chests()[0], Chest {
+        x: 10,
+        y: 16,
+        item: 0x10,
+        rate: 0x32,
+    });
+}
+
+#[test]
+fn shipped_traps_are_decoded() {
+    // The list starts `16 07 00 13 13 23 00 00`: the high nibble of each
+    // slot is the colour and the low nibble the family.
+    let layout = first_layout("DUNG4900.BIN");
+    let rock = |colour| {
+        Some(TrapKind {
+            family: TrapFamily::Rock,
+            colour,
+        })
+    };
+    assert_eq!(layout.traps()[0], Trap {
+        x: 22,
+        y: 7,
+        slots: [
+            None,
+            rock(TrapColour::Yellow),
+            rock(TrapColour::Yellow),
+            rock(TrapColour::Green),
+        ],
+        trailer: [0, 0],
+    });
+    assert_eq!(layout.traps()[0].to_bytes(), [
+        0x16, 0x07, 0x00, 0x13, 0x13, 0x23, 0x00, 0x00
+    ]);
+}
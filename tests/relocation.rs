@@ -0,0 +1,80 @@
+mod common;
+
+use common::{
+    data_file,
+    DUNGEON_FILES,
+};
+use digimon::{
+    parse_ptr,
+    Dungeon,
+    DungeonImage,
+    FreeSpace,
+};
+use std::convert::TryFrom;
+
+#[test]
+fn unchanged_dungeon_is_left_as_is() {
+    for name in &DUNGEON_FILES {
+        let raw = data_file(name);
+        let dungeon = Dungeon::try_from(&raw).unwrap();
+        let mut image = DungeonImage::new(raw.clone()).unwrap();
+        image.update(&dungeon).unwrap();
+        assert_eq!(image.as_bytes(), raw.as_slice(), "{}", name);
+    }
+}
+
+#[test]
+fn grown_list_moves_without_corrupting_neighbours() {
+    let raw = data_file("DUNG4900.BIN");
+    let dungeon = Dungeon::try_from(&raw).unwrap();
+    let mut json = serde_json::to_value(&dungeon).unwrap();
+    let traps = json["layouts"][0]["traps"].as_array_mut().unwrap();
+    traps.extend(std::iter::repeat_n(traps[0].clone(), 8));
+    let edited: Dungeon = serde_json::from_value(json).unwrap();
+
+    let mut image = DungeonImage::new(raw.clone()).unwrap();
+    image.update(&edited).unwrap();
+    let patched = image.into_bytes();
+    // The moved list no longer follows the layout pointer table, so only
+    // its placement differs.
+    let reparsed = Dungeon::try_from(&patched).unwrap();
+    assert_eq!(reparsed.floors(), edited.floors());
+    for (layout, edited) in reparsed.layouts().iter().zip(edited.layouts()) {
+        assert_eq!(layout.floor_plan(), edited.floor_plan());
+        assert_eq!(layout.warps(), edited.warps());
+        assert_eq!(layout.chests(), edited.chests());
+        assert_eq!(layout.traps(), edited.traps());
+        assert_eq!(layout.digimon(), edited.digimon());
+    }
+
+    // Only the old list, the pointer to it, and the new list at the end of
+    // the file may differ.
+    let table_ptr = parse_ptr(&raw[parse_ptr(&raw).unwrap() + 8..]).unwrap();
+    let old_ptr = parse_ptr(&raw[table_ptr + 12..]).unwrap();
+    let new_ptr = parse_ptr(&patched[table_ptr + 12..]).unwrap();
+    assert!(new_ptr >= raw.len());
+    let old_len = (dungeon.layouts()[0].traps().len() + 1) * 8;
+    for (offset, (&before, &after)) in raw.iter().zip(&patched).enumerate() {
+        if (old_ptr..old_ptr + old_len).contains(&offset) {
+            assert_eq!(after, 0x00, "offset 0x{:X}", offset);
+        } else if !(table_ptr + 12..table_ptr + 16).contains(&offset) {
+            assert_eq!(before, after, "offset 0x{:X}", offset);
+        }
+    }
+}
+
+#[test]
+fn freed_space_is_reused() {
+    let mut raw = vec![0xAA; 32];
+    let mut free_space = FreeSpace::default();
+    free_space.free(&mut raw, 8..12);
+    free_space.free(&mut raw, 12..20);
+    assert_eq!(free_space.ranges().len(), 1);
+    assert_eq!(free_space.ranges()[0], 8..20);
+    assert_eq!(&raw[8..20], &[0x00; 12]);
+    assert_eq!(free_space.allocate(&mut raw, 5), 8);
+    assert_eq!(free_space.ranges().len(), 1);
+    assert_eq!(free_space.ranges()[0], 16..20);
+    assert_eq!(free_space.allocate(&mut raw, 6), 32);
+    assert_eq!(raw.len(), 40);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::data_file;
+use digimon::Dungeon;
+use std::convert::TryFrom;
+
+fn assert_round_trip(name: &str) {
+    let raw = data_file(name);
+    let dungeon = Dungeon::try_from(&raw).unwrap();
+    let rebuilt = dungeon.to_bytes();
+    assert_eq!(raw.len(), rebuilt.len(), "{} length differs", name);
+    if let Some(offset) = raw.iter().zip(&rebuilt).position(|(a, b)| a != b) {
+        panic!("{} differs first at offset 0x{:X}", name, offset);
+    }
+    assert_eq!(dungeon, Dungeon::try_from(&rebuilt).unwrap());
+}
+
+#[test]
+fn dung4000_round_trip() {
+    assert_round_trip("DUNG4000.BIN");
+}
+
+#[test]
+fn dung4900_round_trip() {
+    assert_round_trip("DUNG4900.BIN");
+}
+
+#[test]
+fn dung5900_round_trip() {
+    assert_round_trip("DUNG5900.BIN");
+}
+
+#[test]
+fn dung7000_round_trip() {
+    assert_round_trip("DUNG7000.BIN");
+}
+
+#[test]
+fn shared_layouts_are_weighted() {
+    let dungeon = Dungeon::try_from(&data_file("DUNG5900.BIN")).unwrap();
+    let floor = &dungeon.floors()[0];
+    let probabilities = floor.layout_probabilities();
+    assert_eq!(5, probabilities.len());
+    assert_eq!(Some(&0.25), probabilities.get(&floor.layout_slots()[0]));
+    assert_eq!(Some(&0.125), probabilities.get(&floor.layout_slots()[4]));
+    assert!((probabilities.values().sum::<f64>() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn pointers_past_the_end_are_rejected() {
+    let raw = data_file("DUNG4000.BIN");
+    let ptr = |raw: &[u8], offset: usize| {
+        u32::from_le_bytes([
+            raw[offset],
+            raw[offset + 1],
+            raw[offset + 2],
+            raw[offset + 3],
+        ]) as usize
+    };
+    let floor_table = ptr(&raw, 0);
+    let layout_table = ptr(&raw, floor_table + 8);
+    let past_end = (raw.len() as u32 + 0x100).to_le_bytes();
+    // The first floor table, its name, the first layout's floor plan and
+    // its warps.
+    for &offset in &[0, floor_table, layout_table, layout_table + 4] {
+        let mut corrupt = raw.clone();
+        corrupt[offset..offset + 4].copy_from_slice(&past_end);
+        assert!(Dungeon::try_from(&corrupt).is_err(), "0x{:X}", offset);
+    }
+    // A floor table which is cut short by the end of the file.
+    let mut corrupt = raw.clone();
+    corrupt[..4].copy_from_slice(&(raw.len() as u32 - 8).to_le_bytes());
+    assert!(Dungeon::try_from(&corrupt).is_err());
+}
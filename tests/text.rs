@@ -0,0 +1,127 @@
+mod common;
+
+use common::{
+    data_file,
+    DUNGEON_FILES,
+};
+use digimon::{
+    encode_string,
+    parse_string,
+    CharacterTable,
+    Dungeon,
+    GameText,
+};
+use std::convert::TryFrom;
+
+#[test]
+fn encode_glyphs() {
+    assert_eq!(
+        vec![0x1C, 0x0C, 0x1C, 0x12, 0xFD, 0x01, 0x0F, 0xFF],
+        encode_string("SCSI 1F").unwrap()
+    );
+}
+
+#[test]
+fn encode_dictionary_words() {
+    assert_eq!(
+        vec![0xF0, 0x06, 0xFD, 0xF0, 0x0C, 0x45, 0xFF],
+        encode_string("Digimon Tamer!").unwrap()
+    );
+}
+
+#[test]
+fn encode_dictionary_words_within_words() {
+    assert_eq!(
+        vec![0xF0, 0x08, 0x35, 0x28, 0xFF],
+        encode_string("there").unwrap()
+    );
+}
+
+#[test]
+fn encode_control_codes() {
+    assert_eq!(
+        vec![0x11, 0x2C, 0xFE, 0x11, 0x2C, 0xFC, 0xFF],
+        encode_string("Hi<ENTER>Hi<NEW BOX>").unwrap()
+    );
+}
+
+#[test]
+fn encode_illegal_character() {
+    let error = encode_string("Caf\u{e9}").unwrap_err();
+    assert_eq!(
+        "character '\u{e9}' at position 3 cannot be encoded",
+        error.to_string()
+    );
+}
+
+#[test]
+fn loaded_table_overrides_builtin() {
+    let loaded = CharacterTable::from_tbl(
+        "# Fill a gap and replace a glyph\nF001=Tamer\n0C=!\n",
+    )
+    .unwrap();
+    let mut table = CharacterTable::builtin().clone();
+    table.merge(&loaded);
+    let text = GameText::parse_with(&[0xF0, 0x01, 0x0C, 0xFF], &table).unwrap();
+    assert_eq!("Tamer!", text.display_with(&table).to_string());
+    assert_eq!("<0xF001>C", text.to_string());
+    assert_eq!(
+        vec![0xF0, 0x01, 0x0C, 0xFF],
+        table.encode_string("Tamer!").unwrap()
+    );
+}
+
+#[test]
+fn table_file_rejects_malformed_lines() {
+    assert!(CharacterTable::from_tbl("0C").is_err());
+    assert!(CharacterTable::from_tbl("ZZ=a").is_err());
+    assert!(CharacterTable::from_tbl("FF=a").is_err());
+}
+
+#[test]
+fn exact_strings_keep_words_spelled_out() {
+    let raw = [0xF0, 0x06, 0xFD, 0x1D, 0x0C, 0x8F, 0xFF];
+    let text = GameText::parse(&raw).unwrap();
+    assert_eq!("{Digimon} TC<0x8F>", text.to_exact_string());
+    let read = GameText::from_exact_string(&text.to_exact_string()).unwrap();
+    assert_eq!(raw.to_vec(), read.to_bytes());
+    let spelled = GameText::from_exact_string("Digimon").unwrap();
+    assert_eq!(7, spelled.segments().len());
+}
+
+#[test]
+fn exact_strings_escape_codes_which_would_not_read_back() {
+    let plus_sign = GameText::encode("PLUS SIGN").unwrap().to_bytes();
+    assert_eq!(plus_sign, [0x5B, 0xFF]);
+    let mut spelled = GameText::from_exact_string("PLUS").unwrap().to_bytes();
+    spelled.pop();
+    spelled.push(0xFD);
+    spelled.extend(GameText::from_exact_string("SIGN").unwrap().to_bytes());
+    for raw in [vec![0x0B, 0x56, 0x0C, 0xFF], plus_sign, spelled] {
+        let text = GameText::parse(&raw).unwrap();
+        let exact = text.to_exact_string();
+        let read = GameText::from_exact_string(&exact).unwrap();
+        assert_eq!(raw, read.to_bytes(), "{:?}", exact);
+    }
+    let text = GameText::parse(&[0x0B, 0x56, 0x0C, 0xFF]).unwrap();
+    assert_eq!("B<0x56>C", text.to_exact_string());
+}
+
+#[test]
+fn exact_strings_reject_end_and_word_codes() {
+    assert!(GameText::from_exact_string("A<0xFF>B").is_err());
+    assert!(GameText::from_exact_string("<0xF0>A").is_err());
+    assert!(GameText::from_exact_string("<0xF001>A").is_ok());
+}
+
+#[test]
+fn floor_names_round_trip() {
+    for name in &DUNGEON_FILES {
+        let dungeon = Dungeon::try_from(&data_file(name)).unwrap();
+        for floor in dungeon.floors() {
+            let name = floor.name().to_string();
+            let encoded = encode_string(&name).unwrap();
+            assert_eq!(name, parse_string(&encoded).unwrap());
+        }
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{
+    data_file,
+    first_layout,
+    DUNGEON_FILES,
+};
+use digimon::{
+    Dungeon,
+    Layout,
+    Severity,
+    Tile,
+};
+use std::convert::TryFrom;
+
+#[test]
+fn shipped_dungeons_have_no_errors() {
+    for name in &DUNGEON_FILES {
+        let dungeon = Dungeon::try_from(&data_file(name)).unwrap();
+        let errors = dungeon
+            .validate()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{}: {:?}", name, errors);
+    }
+}
+
+#[test]
+fn layout_without_warps_has_errors() {
+    let mut json = serde_json::to_value(first_layout("DUNG4000.BIN")).unwrap();
+    json["warps"] = serde_json::json!([]);
+    let layout: Layout = serde_json::from_value(json).unwrap();
+    let messages = layout
+        .validate()
+        .into_iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>();
+    assert!(messages.contains(&String::from("error: no spawn warp")));
+    assert!(
+        messages.contains(&String::from("error: no next floor or exit warp"))
+    );
+}
+
+#[test]
+fn misplaced_entities_are_found() {
+    let mut json = serde_json::to_value(first_layout("DUNG4000.BIN")).unwrap();
+    json["chests"] = serde_json::json!([
+        { "x": 0, "y": 0, "item": 1, "rate": 1 },
+        { "x": 64, "y": 3, "item": 1, "rate": 1 },
+    ]);
+    let warp = json["warps"][0].clone();
+    json["warps"].as_array_mut().unwrap().push(warp.clone());
+    let layout: Layout = serde_json::from_value(json).unwrap();
+    let messages = layout
+        .validate()
+        .into_iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>();
+    assert!(messages
+        .contains(&String::from("error: (0, 0): chest on an empty tile")));
+    assert!(messages.contains(&String::from(
+        "error: (64, 3): chest outside the 64x48 floor plan"
+    )));
+    assert!(messages.contains(&format!(
+        "warning: ({}, {}): warp on the same tile as a warp",
+        warp["x"], warp["y"]
+    )));
+}
+
+#[test]
+fn entities_on_tile_code_9_are_on_an_empty_tile() {
+    let mut layout = first_layout("DUNG4000.BIN");
+    let warp = layout.warps()[0];
+    layout
+        .floor_plan_mut()
+        .set_tile(usize::from(warp.x), usize::from(warp.y), Tile::Unknown(0x9))
+        .unwrap();
+    let messages = layout
+        .validate()
+        .into_iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>();
+    assert!(messages.contains(&format!(
+        "error: ({}, {}): warp on an empty tile",
+        warp.x, warp.y
+    )));
+}